@@ -0,0 +1,86 @@
+// Backend counterpart of `src/utils/writeHostsToSystem.ts`.
+//
+// Keeps whatever the system hosts file holds above our marker and replaces
+// everything below it with the composed rules, or, in the `whole_file` write
+// mode, replaces the file entirely. The UI calls this through the
+// `apply_system_hosts` command and prompts for a password when it fails.
+use crate::compose::ComposeOptions;
+use crate::settings::{self, Settings, WriteMode};
+use crate::{audit, commands, report, resolver};
+use serde_json::{json, Value};
+
+pub const CONTENT_START: &str = "# --- HOSTSMANAGER_CONTENT_START ---\n";
+const LEGACY_CONTENT_STARTS: [&str; 2] = [
+    "# --- SWITCHHOSTS_CONTENT_START ---\n",
+    "# --- SWEETHOSTS_CONTENT_START ---\n",
+];
+
+/// The part of the system hosts file that was not written by us.
+pub fn origin_content(system: &str) -> &str {
+    let first = std::iter::once(CONTENT_START)
+        .chain(LEGACY_CONTENT_STARTS)
+        .filter_map(|marker| system.find(marker))
+        .min();
+    match first {
+        Some(i) => system[..i].trim_end(),
+        None => system,
+    }
+}
+
+pub fn build_system_hosts(origin: &str, content: &str) -> String {
+    if content.is_empty() {
+        return format!("{}\n", origin);
+    }
+    format!("{}\n\n\n\n{}\n\n{}", origin, CONTENT_START, content)
+}
+
+/// The options the rules are composed with for the system hosts file or the
+/// resolver.
+pub fn compose_options(s: &Settings) -> ComposeOptions {
+    ComposeOptions {
+        ipv6_companions: s.ipv6_companions,
+        // the resolver serves wildcards itself; the hosts file needs them expanded
+        keep_patterns: s.resolver_mode,
+        ..Default::default()
+    }
+}
+
+/// Composes the enabled rules and writes them to the system hosts file,
+/// auditing the write as `action`. In resolver mode the rules are handed to the
+/// embedded resolver instead.
+///
+/// Returns the same JSON shape as `set_system_hosts`.
+pub fn apply_system_hosts(password: Option<String>, action: audit::Action) -> Value {
+    let s = settings::effective();
+    let content = match commands::get_content_of_list(Some(compose_options(&s))) {
+        Ok(c) => c,
+        Err(message) => {
+            return json!({ "success": false, "code": "compose_failed", "message": message })
+        }
+    };
+    if s.resolver_mode {
+        return match resolver::apply(&content, &s) {
+            // the hosts file is untouched; report it as it is
+            Ok(addr) => {
+                report::record_applied(&content);
+                json!({
+                "success": true,
+                "new_content": commands::get_system_hosts(),
+                "resolver": addr.to_string()
+                })
+            }
+            Err(e) => {
+                json!({ "success": false, "code": "resolver_failed", "message": e.to_string() })
+            }
+        };
+    }
+    let next = match s.write_mode {
+        WriteMode::ManagedBlock => {
+            let system = commands::get_system_hosts();
+            build_system_hosts(origin_content(&system), &content)
+        }
+        WriteMode::WholeFile if content.ends_with('\n') => content,
+        WriteMode::WholeFile => format!("{}\n", content),
+    };
+    commands::write_system_hosts(next, password, action)
+}
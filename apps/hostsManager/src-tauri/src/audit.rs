@@ -0,0 +1,121 @@
+// Append-only audit trail of privileged hosts operations.
+//
+// Every attempt to change the system hosts file is appended to `audit.log` in
+// the data directory as one JSON object per line, whether it succeeded or not.
+// Records carry the SHA-256 of the content that was (to be) written, never the
+// content itself, so the log can be shipped to a security team as-is. The file
+// is only ever opened in append mode; nothing in the app rewrites or trims it.
+use crate::commands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// `set_system_hosts` called from the UI.
+    Write,
+    /// Writing through `sudo` after a direct write was refused.
+    Escalate,
+    /// Restoring an earlier version of the system hosts file.
+    Rollback,
+    /// Background re-apply without user interaction (e.g. rule schedules).
+    Reapply,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Record {
+    pub time_ms: u64,
+    pub action: Action,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub path: String,
+    pub content_sha256: String,
+    pub user: String,
+    pub pid: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Filter {
+    pub action: Option<Action>,
+    pub success: Option<bool>,
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    /// Maximum number of records, newest first.
+    pub limit: Option<usize>,
+}
+
+fn log_path() -> PathBuf {
+    commands::data_dir().join("audit.log")
+}
+
+pub fn sha256_hex(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// Appends a record built from a `set_system_hosts`-shaped result.
+pub fn record(action: Action, path: &str, content: &str, result: &Value) {
+    let text = |k: &str| result.get(k).and_then(|v| v.as_str()).map(String::from);
+    let rec = Record {
+        time_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        action,
+        success: result.get("success").and_then(|v| v.as_bool()) == Some(true),
+        code: text("code"),
+        message: text("message"),
+        path: path.to_string(),
+        content_sha256: sha256_hex(content),
+        user: current_user(),
+        pid: std::process::id(),
+    };
+    if let Err(e) = append(&rec) {
+        eprintln!("hostsmanager: cannot write audit log: {}", e);
+    }
+}
+
+fn append(rec: &Record) -> std::io::Result<()> {
+    commands::ensure_data_dir()?;
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path())?;
+    let line = serde_json::to_string(rec).map_err(std::io::Error::other)?;
+    writeln!(f, "{}", line)
+}
+
+/// Reads the log, skipping lines that do not parse.
+pub fn query(filter: &Filter) -> Vec<Record> {
+    let text = fs::read_to_string(log_path()).unwrap_or_default();
+    let mut out: Vec<Record> = text
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Record>(l).ok())
+        .filter(|r| filter.action.is_none_or(|a| r.action == a))
+        .filter(|r| filter.success.is_none_or(|s| r.success == s))
+        .filter(|r| filter.since_ms.is_none_or(|t| r.time_ms >= t))
+        .filter(|r| filter.until_ms.is_none_or(|t| r.time_ms <= t))
+        .collect();
+    out.reverse();
+    if let Some(limit) = filter.limit {
+        out.truncate(limit);
+    }
+    out
+}
@@ -0,0 +1,184 @@
+// Conversion of subscribed/imported lists into hosts entries.
+//
+// Remote and imported sources may be plain hosts files, adblock-style filter
+// lists (`||ads.example.com^`, `@@||allowed.example.com^`) or one domain per
+// line. Every blocked domain becomes a `0.0.0.0 domain` entry. Domains on the
+// item's `allowlist` (or adblock `@@` exceptions) are dropped, including their
+// subdomains. Lists with 100k+ lines are common, so conversion is a single pass
+// into a pre-sized buffer with a hash set for de-duplication.
+use crate::hosts::{self, Line};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+pub const BLOCK_IP: &str = "0.0.0.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Hosts,
+    Adblock,
+    Domains,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hosts" => Ok(Format::Hosts),
+            "adblock" => Ok(Format::Adblock),
+            "domains" => Ok(Format::Domains),
+            _ => Err(format!("unknown list format: {}", s)),
+        }
+    }
+}
+
+/// Guesses the format from the first meaningful lines.
+pub fn detect(text: &str) -> Format {
+    let mut sample = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .take(50)
+        .peekable();
+    if sample.peek().is_none() {
+        return Format::Hosts;
+    }
+    let mut entries = 0;
+    for line in sample {
+        if line.starts_with("||") || line.starts_with("@@") || line.starts_with("[Adblock") {
+            return Format::Adblock;
+        }
+        if matches!(hosts::parse_line(line), Line::Entry { .. }) {
+            entries += 1;
+        }
+    }
+    if entries > 0 {
+        Format::Hosts
+    } else {
+        Format::Domains
+    }
+}
+
+pub fn is_valid_domain(d: &str) -> bool {
+    !d.is_empty()
+        && d.len() <= 253
+        && d.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+fn is_allowed(domain: &str, allow: &HashSet<String>) -> bool {
+    let mut d = domain;
+    loop {
+        if allow.contains(d) {
+            return true;
+        }
+        match d.find('.') {
+            Some(i) => d = &d[i + 1..],
+            None => return false,
+        }
+    }
+}
+
+// Options that leave a rule applying to every request to the domain. Any
+// other (`third-party`, `script`, `domain=...`) narrows it to something a
+// hosts entry cannot express.
+const WHOLE_DOMAIN_OPTIONS: [&str; 5] = ["important", "all", "document", "doc", "popup"];
+
+// `||domain^` with an optional list of `WHOLE_DOMAIN_OPTIONS`; anything with
+// other options, paths, wildcards or cosmetic selectors is not expressible in
+// hosts.
+fn adblock_domain(rule: &str) -> Option<&str> {
+    let rest = rule.strip_prefix("||")?;
+    let (rest, options) = rest.split_once('$').unwrap_or((rest, ""));
+    if !options.is_empty()
+        && !options
+            .split(',')
+            .all(|o| WHOLE_DOMAIN_OPTIONS.contains(&o.trim().to_ascii_lowercase().as_str()))
+    {
+        return None;
+    }
+    let domain = rest.strip_suffix('^').unwrap_or(rest);
+    is_valid_domain(domain).then_some(domain)
+}
+
+/// Converts `text` into hosts content. Returns the content and the number of
+/// entries it contains.
+pub fn convert(text: &str, format: Format, allowlist: &[String]) -> (String, usize) {
+    let mut allow: HashSet<String> = allowlist
+        .iter()
+        .map(|d| d.trim().to_ascii_lowercase())
+        .collect();
+    let mut blocked: Vec<String> = Vec::new();
+
+    match format {
+        Format::Hosts => {
+            // Hosts entries keep their own IP; only allowlisted names are dropped.
+            let mut out = String::with_capacity(text.len());
+            let mut seen = HashSet::new();
+            let mut count = 0;
+            for raw in text.lines() {
+                let Line::Entry { ip_text, hosts, .. } = hosts::parse_line(raw) else {
+                    continue;
+                };
+                let kept: Vec<&str> = hosts
+                    .into_iter()
+                    .filter(|h| !is_allowed(&h.to_ascii_lowercase(), &allow))
+                    .filter(|h| seen.insert((ip_text, h.to_ascii_lowercase())))
+                    .collect();
+                if kept.is_empty() {
+                    continue;
+                }
+                out.push_str(ip_text);
+                for h in kept {
+                    out.push(' ');
+                    out.push_str(h);
+                }
+                out.push('\n');
+                count += 1;
+            }
+            return (out, count);
+        }
+        Format::Adblock => {
+            for raw in text.lines() {
+                let line = raw.trim();
+                if let Some(exception) = line.strip_prefix("@@") {
+                    if let Some(d) = adblock_domain(exception) {
+                        allow.insert(d.to_ascii_lowercase());
+                    }
+                } else if let Some(d) = adblock_domain(line) {
+                    blocked.push(d.to_ascii_lowercase());
+                }
+            }
+        }
+        Format::Domains => {
+            for raw in text.lines() {
+                let line = raw.split('#').next().unwrap_or("").trim();
+                if is_valid_domain(line) {
+                    blocked.push(line.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+
+    let mut out = String::with_capacity(blocked.len() * (BLOCK_IP.len() + 24));
+    let mut seen = HashSet::with_capacity(blocked.len());
+    let mut count = 0;
+    for d in &blocked {
+        if is_allowed(d, &allow) || !seen.insert(d.as_str()) {
+            continue;
+        }
+        out.push_str(BLOCK_IP);
+        out.push(' ');
+        out.push_str(d);
+        out.push('\n');
+        count += 1;
+    }
+    (out, count)
+}
@@ -3,30 +3,43 @@
 // SwitchHosts/src/common/data.d.ts (IHostsListObject, IHostsContentObject, ITrashcanObject, etc.)
 // We keep storage as serde_json::Value for flexibility but preserve fields like
 // `id`, `title`, `on`, `type`, `children`, `content`, `add_time_ms` to maintain compatibility.
+use crate::apply;
+use crate::audit;
+use crate::blocklist;
+use crate::compose;
+use crate::deeplink;
+use crate::export;
+use crate::flags;
+use crate::history;
+use crate::items;
+use crate::paths;
+use crate::report;
+use crate::resolver;
+use crate::scheduler;
+use crate::search;
+use crate::settings;
+use crate::share;
+use crate::store;
+use crate::sync;
+use crate::tree;
+use crate::validate;
+use serde::Serialize;
 use serde_json::json;
-use serde_json::Value;
-use std::env;
+use serde_json::{Map, Value};
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
 
-fn data_dir() -> PathBuf {
-    if let Ok(dir) = env::var("HOSTSMANAGER_DATA_DIR") {
-        return PathBuf::from(dir);
-    }
-
-    if cfg!(target_os = "macos") || cfg!(target_os = "linux") {
-        if let Ok(home) = env::var("HOME") {
-            return PathBuf::from(home).join(".hostsmanager");
-        }
-    }
-
-    // fallback to current dir
-    PathBuf::from(".").join("hostsmanager")
+pub(crate) fn data_dir() -> PathBuf {
+    settings::effective()
+        .data_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(paths::default_data_dir)
 }
 
-fn ensure_data_dir() -> std::io::Result<()> {
+pub(crate) fn ensure_data_dir() -> std::io::Result<()> {
     let d = data_dir();
     if !d.exists() {
         fs::create_dir_all(&d)?;
@@ -34,6 +47,10 @@ fn ensure_data_dir() -> std::io::Result<()> {
     Ok(())
 }
 
+pub(crate) fn content_path(id: &str) -> PathBuf {
+    data_dir().join(format!("hosts_content_{}.txt", id))
+}
+
 fn read_json_array(p: PathBuf) -> Vec<Value> {
     if !p.exists() {
         return vec![];
@@ -44,13 +61,6 @@ fn read_json_array(p: PathBuf) -> Vec<Value> {
     }
 }
 
-fn write_json_array(p: PathBuf, v: &Vec<Value>) -> bool {
-    match fs::write(p, serde_json::to_string(v).unwrap_or_default()) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
-}
-
 #[tauri::command]
 pub fn ping() -> String {
     "pong".to_string()
@@ -67,53 +77,261 @@ pub fn get_list() -> Vec<Value> {
     read_json_array(p)
 }
 
+/// The list together with the revision it was read at, for a later
+/// `set_list` that must not overwrite someone else's change.
+#[derive(Serialize, Debug, Clone)]
+pub struct ListSnapshot {
+    pub list: Vec<Value>,
+    pub revision: String,
+}
+
 #[tauri::command]
-pub fn set_list(v: Vec<Value>) -> bool {
-    if let Err(_) = ensure_data_dir() {
-        return false;
+pub fn get_list_with_revision() -> ListSnapshot {
+    let bytes = fs::read(store::list_path()).unwrap_or_default();
+    ListSnapshot {
+        list: serde_json::from_slice(&bytes).unwrap_or_default(),
+        revision: store::revision_of(&bytes),
     }
-    let mut p = data_dir();
-    p.push("list.json");
-    write_json_array(p, &v)
 }
 
 #[tauri::command]
-pub fn get_content_of_list() -> String {
-    // read list.json and collect ids where on == true
+pub fn get_list_revision() -> String {
+    store::list_revision()
+}
+
+/// Saves the list and returns its new revision. When `revision` is given the
+/// write is rejected with a conflict if the stored list has moved on since.
+#[tauri::command]
+pub fn set_list(v: Vec<Value>, revision: Option<String>) -> Result<String, store::StoreError> {
+    let before = get_list();
+    let revision = store::save_list(&v, revision)?;
+    sync::record(&sync::describe_list_change(&before, &v));
+    Ok(revision)
+}
+
+/// Adds a rule or folder under `parent` (top level if `None`) at `index` (the
+/// end if `None`), optionally with initial content. Returns the stored item.
+#[tauri::command]
+pub fn create_item(
+    parent: Option<String>,
+    item: Value,
+    index: Option<usize>,
+    content: Option<String>,
+) -> Result<Value, items::ItemError> {
+    items::create(parent.as_deref(), item, index, content.as_deref())
+}
+
+/// Merges `patch` into an item; `null` values remove fields.
+#[tauri::command]
+pub fn update_item(id: String, patch: Map<String, Value>) -> Result<Value, items::ItemError> {
+    items::update(&id, patch)
+}
+
+#[tauri::command]
+pub fn move_item(
+    id: String,
+    parent: Option<String>,
+    index: Option<usize>,
+) -> Result<(), items::ItemError> {
+    items::move_to(&id, parent.as_deref(), index)
+}
+
+/// Deletes an item with everything below it; returns the removed ids.
+#[tauri::command]
+pub fn delete_item(id: String) -> Result<Vec<String>, items::ItemError> {
+    items::delete(&id)
+}
+
+/// Copies an item with everything below it to right after the original.
+#[tauri::command]
+pub fn duplicate_item(id: String) -> Result<Value, items::ItemError> {
+    items::duplicate(&id)
+}
+
+fn read_variables() -> compose::Vars {
+    let p = data_dir().join("variables.json");
+    fs::read_to_string(p)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn load_content(id: &str) -> Option<String> {
+    fs::read_to_string(content_path(id)).ok()
+}
+
+#[tauri::command]
+pub fn get_content_of_list(opts: Option<compose::ComposeOptions>) -> Result<String, String> {
+    // compose the content of every rule where on == true
+    get_composition(opts).map(|c| c.content)
+}
+
+/// The composed content together with its source map and unresolved variables.
+#[tauri::command]
+pub fn get_composition(opts: Option<compose::ComposeOptions>) -> Result<compose::Composed, String> {
     let list = get_list();
-    let mut ids: Vec<String> = Vec::new();
-
-    fn collect(items: &Vec<Value>, out: &mut Vec<String>) {
-        for item in items {
-            if let Some(on) = item.get("on").and_then(|v| v.as_bool()) {
-                if on {
-                    if let Some(id) = item.get("id").and_then(|v| v.as_str()) {
-                        out.push(id.to_string());
-                    }
-                }
-            }
-            if let Some(children) = item.get("children").and_then(|v| v.as_array()) {
-                collect(children, out);
-            }
+    let mut opts = opts.unwrap_or_default();
+    opts.pattern_hosts = settings::effective().pattern_hosts;
+    compose::compose(&list, &read_variables(), &opts, load_content).map_err(|e| e.to_string())
+}
+
+/// The enabled rules as dnsmasq, unbound or CoreDNS configuration, or as a
+/// JSON object of hostname -> addresses (`format`: dnsmasq, unbound, coredns,
+/// json).
+#[tauri::command]
+pub fn export_as(format: String) -> Result<String, String> {
+    let format: export::ExportFormat = format.parse()?;
+    Ok(export::render(&get_content_of_list(None)?, format))
+}
+
+/// A rule as a checksummed payload, an import link and a text block. A
+/// `system` rule shares the system hosts file it mirrors.
+#[tauri::command]
+pub fn share_rule(id: String) -> Result<share::Share, share::ShareError> {
+    share::share(&get_list(), &id, get_hosts_content(id.clone()))
+}
+
+/// Decodes and checks a shared rule (payload, link or text block) without
+/// importing it.
+#[tauri::command]
+pub fn preview_shared_rule(text: String) -> Result<share::Preview, share::ShareError> {
+    share::preview(&text, &get_list())
+}
+
+/// Imports a shared rule as a new disabled rule; returns the stored item.
+#[tauri::command]
+pub fn import_shared_rule(
+    text: String,
+    parent: Option<String>,
+) -> Result<Value, share::ShareError> {
+    share::import(&text, parent.as_deref())
+}
+
+/// Per-rule statistics (entries, hostnames, addresses, invalid lines,
+/// conflicts, last modified and applied) and the size of the composed output.
+#[tauri::command]
+pub fn get_report() -> report::Report {
+    let list = get_list();
+    let opts = apply::compose_options(&settings::effective());
+    report::build(
+        &list,
+        |item| match tree::id(item) {
+            Some(_) if flags::is_system(item) => Some(get_system_hosts()),
+            Some(id) => load_content(id),
+            None => None,
+        },
+        get_content_of_list(Some(opts)),
+    )
+}
+
+#[tauri::command]
+pub fn get_variables() -> compose::Vars {
+    read_variables()
+}
+
+#[tauri::command]
+pub fn set_variables(v: compose::Vars) -> Result<bool, String> {
+    for (name, value) in &v {
+        if !compose::is_valid_var_name(name) {
+            return Err(format!("invalid variable name: {}", name));
+        }
+        if value.contains('\n') {
+            return Err(format!("variable {} must be a single line", name));
         }
     }
+    ensure_data_dir().map_err(|e| e.to_string())?;
+    let p = data_dir().join("variables.json");
+    fs::write(p, serde_json::to_string(&v).unwrap_or_default()).map_err(|e| e.to_string())?;
+    sync::record("Edit variables");
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn check_variables() -> Vec<compose::Unresolved> {
+    let list = get_list();
+    compose::check_variables(&list, &read_variables(), load_content)
+}
 
-    collect(&list, &mut ids);
+// Converts `text` per the item's `format`/`allowlist` and stores it as the
+// item's content. Returns the number of entries written.
+fn store_blocklist(item: &Value, text: &str, format: Option<&str>) -> Result<usize, String> {
+    let id = tree::id(item).ok_or("item has no id")?;
+    let format = match format.or_else(|| item.get("format").and_then(|v| v.as_str())) {
+        None | Some("auto") => blocklist::detect(text),
+        Some(f) => f.parse()?,
+    };
+    let allowlist: Vec<String> = item
+        .get("allowlist")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let (content, count) = blocklist::convert(text, format, &allowlist);
+    {
+        let _lock = store::lock().map_err(|e| e.to_string())?;
+        flags::check_content(&get_list(), id).map_err(|e| e.to_string())?;
+        store::write_atomic(&content_path(id), content.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    sync::record(&format!("Refresh {}", sync::label(item)));
+    Ok(count)
+}
 
-    let mut contents: Vec<String> = Vec::new();
-    for id in ids {
-        let mut p = data_dir();
-        p.push(format!("hosts_content_{}.txt", id));
-        if let Ok(s) = fs::read_to_string(p) {
-            contents.push(s);
-        }
+fn fetch_source(url: &str) -> Result<String, String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e));
     }
+    reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(60))
+        .build()
+        .and_then(|c| c.get(url).send())
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.text())
+        .map_err(|e| e.to_string())
+}
 
-    let content = contents.join("\n\n");
-    content
+/// Re-downloads a `type: "remote"` item from its `url`.
+#[tauri::command(async)]
+pub fn refresh_remote(id: String) -> Result<usize, String> {
+    let ListSnapshot { mut list, revision } = get_list_with_revision();
+    let item = tree::find(&list, &id).ok_or(format!("no rule with id {}", id))?;
+    let url = item
+        .get("url")
+        .and_then(|v| v.as_str())
+        .ok_or(format!("rule {} has no url", id))?;
+    let text = fetch_source(url)?;
+    let count = store_blocklist(item, &text, None)?;
+
+    if let Some(obj) = tree::find_mut(&mut list, &id).and_then(|v| v.as_object_mut()) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        obj.insert("last_refresh_ms".to_string(), json!(now));
+    }
+    set_list(list, Some(revision)).map_err(|e| e.to_string())?;
+    Ok(count)
 }
 
-pub fn get_path_of_system_hosts() -> String {
+/// Imports a hosts, adblock or domain-per-line list pasted or read by the UI.
+#[tauri::command]
+pub fn import_blocklist(id: String, text: String, format: Option<String>) -> Result<usize, String> {
+    let list = get_list();
+    let item = tree::find(&list, &id).ok_or(format!("no rule with id {}", id))?;
+    store_blocklist(item, &text, format.as_deref())
+}
+
+/// Lint a rule's content (invalid lines, single-stack hostnames).
+#[tauri::command]
+pub fn validate_hosts_content(content: String) -> Vec<validate::Warning> {
+    validate::validate(&content)
+}
+
+#[tauri::command]
+pub fn search_rules(query: String) -> Result<Vec<search::SearchHit>, String> {
+    let q = search::Query::parse(&query)?;
+    let list = get_list();
+    Ok(search::search(&list, &q, load_content))
+}
+
+fn real_system_hosts_path() -> String {
     if cfg!(target_os = "windows") {
         std::env::var("windir")
             .map(|w| format!("{}\\system32\\drivers\\etc\\hosts", w))
@@ -123,73 +341,156 @@ pub fn get_path_of_system_hosts() -> String {
     }
 }
 
+// The safe-mode stand-in for the system hosts file, seeded from the real one
+// the first time it is needed.
+fn sandbox_hosts_path(s: &settings::Settings) -> PathBuf {
+    let p = s
+        .safe_hosts_path
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data_dir().join("sandbox").join("hosts"));
+    if !p.exists() {
+        if let Some(dir) = p.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let seed = fs::read_to_string(real_system_hosts_path()).unwrap_or_default();
+        let _ = fs::write(&p, seed);
+    }
+    p
+}
+
+/// The file every read and write of "the system hosts file" goes to: the real
+/// one, or the sandbox file in safe mode.
+#[tauri::command]
+pub fn get_path_of_system_hosts() -> String {
+    let s = settings::effective();
+    if s.safe_mode {
+        sandbox_hosts_path(&s).to_string_lossy().to_string()
+    } else {
+        real_system_hosts_path()
+    }
+}
+
+// Best effort: the hosts file is already written when this runs, so a missing
+// tool or a refused flush is only reported back.
+fn flush_dns_cache() -> bool {
+    let cmds: &[&[&str]] = if cfg!(target_os = "windows") {
+        &[&["ipconfig", "/flushdns"]]
+    } else if cfg!(target_os = "macos") {
+        &[
+            &["dscacheutil", "-flushcache"],
+            &["killall", "-HUP", "mDNSResponder"],
+        ]
+    } else {
+        &[&["resolvectl", "flush-caches"]]
+    };
+    cmds.iter().all(|c| {
+        Command::new(c[0])
+            .args(&c[1..])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
 #[tauri::command]
 pub fn get_system_hosts() -> String {
     let p = get_path_of_system_hosts();
     fs::read_to_string(p).unwrap_or_default()
 }
 
+/// The content of a rule; a `system` rule mirrors the system hosts file.
 #[tauri::command]
 pub fn get_hosts_content(id: String) -> String {
     if let Err(_) = ensure_data_dir() {
         return String::new();
     }
-    let mut p = data_dir();
-    p.push(format!("hosts_content_{}.txt", id));
-    match fs::read_to_string(p) {
+    if tree::find(&get_list(), &id).is_some_and(flags::is_system) {
+        return get_system_hosts();
+    }
+    match fs::read_to_string(content_path(&id)) {
         Ok(s) => s,
         Err(_) => String::new(),
     }
 }
 
+/// Saves a rule's content; false when it could not be written. `system` and
+/// `locked` rules are refused with their flag as the error code.
 #[tauri::command]
-pub fn set_hosts_content(id: String, content: String) -> bool {
-    if let Err(_) = ensure_data_dir() {
-        return false;
+pub fn set_hosts_content(id: String, content: String) -> Result<bool, flags::FlagError> {
+    {
+        let Ok(_lock) = store::lock() else {
+            return Ok(false);
+        };
+        flags::check_content(&get_list(), &id)?;
+        if store::write_atomic(&content_path(&id), content.as_bytes()).is_err() {
+            return Ok(false);
+        }
     }
-    let mut p = data_dir();
-    p.push(format!("hosts_content_{}.txt", id));
-    fs::write(p, content).is_ok()
+    let list = get_list();
+    let label = tree::find(&list, &id).map_or(id.as_str(), sync::label);
+    sync::record_edit(&id, &format!("Edit content of {}", label));
+    Ok(true)
 }
 
 #[tauri::command]
 pub fn set_system_hosts(content: String, opts: Option<String>) -> Value {
+    write_system_hosts(content, opts, audit::Action::Write)
+}
+
+/// Writes the system hosts file and records the attempt in the audit log under
+/// `action`. On success the replaced content goes to history and, if enabled,
+/// the DNS cache is flushed.
+pub(crate) fn write_system_hosts(
+    content: String,
+    opts: Option<String>,
+    action: audit::Action,
+) -> Value {
+    let s = settings::effective();
+    let content = s.line_endings.apply(&content);
+    let mut res = write_hosts_file(&content, opts, s.safe_mode);
+    if res.get("success").and_then(|v| v.as_bool()) == Some(true) {
+        let old = res
+            .get("old_content")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if let Err(e) = history::save(old, s.history_retention) {
+            eprintln!("hostsmanager: cannot save hosts history: {}", e);
+        }
+        report::record_applied(&content);
+        if s.flush_dns && !s.safe_mode {
+            res["dns_flushed"] = json!(flush_dns_cache());
+        }
+    }
+    audit::record(action, &get_path_of_system_hosts(), &content, &res);
+    res
+}
+
+fn write_hosts_file(content: &str, opts: Option<String>, safe_mode: bool) -> Value {
     let sys_path = get_path_of_system_hosts();
 
     // read old content
     let old_content = fs::read_to_string(&sys_path).unwrap_or_default();
 
-    // respect safe mode
-    let safe_mode = std::env::var("HOSTSMANAGER_SAFE_MODE").unwrap_or_default() == "1"
-        || std::env::var("SWEETHOSTS_SAFE_MODE").unwrap_or_default() == "1";
-    if safe_mode {
-        // write to temp file instead
-        let mut tmp = env::temp_dir();
-        tmp.push(format!(
-            "hostsmanager_safe_{}.hosts",
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_millis())
-                .unwrap_or(0)
-        ));
-        let _ = fs::write(&tmp, &content);
-        return json!({ "success": true, "old_content": old_content, "new_content": content, "safe_path": tmp.to_string_lossy() });
-    }
-
     // try direct write first
-    match fs::write(&sys_path, &content) {
+    match fs::write(&sys_path, content) {
         Ok(_) => {
             // success
             let res =
                 json!({ "success": true, "old_content": old_content, "new_content": content });
             return res;
         }
+        Err(e) if safe_mode => {
+            // the sandbox is a plain user file; escalating would never help
+            return json!({ "success": false, "code": "write_failed", "message": e.to_string() });
+        }
         Err(_) => {
             // try sudo fallback on unix
             if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
                 if let Some(pw) = opts {
                     // write tmp file
-                    let mut tmp = env::temp_dir();
+                    let mut tmp = paths::cache_dir();
+                    let _ = fs::create_dir_all(&tmp);
                     let rand_part = format!(
                         "{}",
                         SystemTime::now()
@@ -198,7 +499,7 @@ pub fn set_system_hosts(content: String, opts: Option<String>) -> Value {
                             .unwrap_or(0)
                     );
                     tmp.push(format!("swh_{}.txt", rand_part));
-                    let _ = fs::write(&tmp, &content);
+                    let _ = fs::write(&tmp, content);
 
                     let cmd = format!(
                         "echo '{}' | sudo -S sh -c 'cat \"{}\" > \"{}\" && chmod 644 \"{}\"'",
@@ -212,15 +513,20 @@ pub fn set_system_hosts(content: String, opts: Option<String>) -> Value {
                     // cleanup tmp
                     let _ = fs::remove_file(&tmp);
 
-                    if let Ok(o) = output {
-                        if o.status.success() {
-                            let res = json!({ "success": true, "old_content": old_content, "new_content": content });
-                            return res;
-                        } else {
+                    let res = match output {
+                        Ok(o) if o.status.success() => {
+                            json!({ "success": true, "old_content": old_content, "new_content": content })
+                        }
+                        Ok(o) => {
                             let msg = String::from_utf8_lossy(&o.stderr).to_string();
-                            return json!({ "success": false, "code": "no_access", "message": msg });
+                            json!({ "success": false, "code": "no_access", "message": msg })
+                        }
+                        Err(e) => {
+                            json!({ "success": false, "code": "no_access", "message": e.to_string() })
                         }
-                    }
+                    };
+                    audit::record(audit::Action::Escalate, &sys_path, content, &res);
+                    return res;
                 }
             }
         }
@@ -229,14 +535,140 @@ pub fn set_system_hosts(content: String, opts: Option<String>) -> Value {
     json!({ "success": false, "code": "no_access" })
 }
 
+/// Composes the enabled rules and writes them to the system hosts file using
+/// the configured write mode.
 #[tauri::command]
-pub fn close_main_window() -> bool {
-    // Window control should be handled via tauri::Window in app code; noop
-    true
+pub fn apply_system_hosts(opts: Option<String>) -> Value {
+    apply::apply_system_hosts(opts, audit::Action::Write)
+}
+
+#[tauri::command]
+pub fn get_history() -> Vec<history::Entry> {
+    history::list()
+}
+
+#[tauri::command]
+pub fn get_history_content(id: String) -> Option<String> {
+    history::read(&id)
+}
+
+/// Restores a saved version of the system hosts file.
+#[tauri::command]
+pub fn rollback_history(id: String, opts: Option<String>) -> Value {
+    match history::read(&id) {
+        Some(content) => write_system_hosts(content, opts, audit::Action::Rollback),
+        None => json!({ "success": false, "code": "not_found" }),
+    }
 }
 
 #[tauri::command]
-pub fn quit() -> bool {
-    // Tauri app exit should be invoked from main; noop
+pub fn get_settings() -> settings::SettingsView {
+    settings::view()
+}
+
+#[tauri::command]
+pub fn set_settings(v: settings::Settings) -> Result<settings::SettingsView, String> {
+    let before = settings::effective();
+    settings::save(&v)?;
+    let view = settings::view();
+    // the resolver starts (or moves to a new port) on the next apply
+    if !view.effective.resolver_mode {
+        resolver::stop();
+    }
+    // the first commit (and the first for a new remote) takes the rules as
+    // they are; other settings have nothing to do with sync
+    let now = &view.effective;
+    if now.sync_enabled && (!before.sync_enabled || now.sync_remote != before.sync_remote) {
+        sync::record("Start syncing rules");
+    }
+    Ok(view)
+}
+
+/// Merges the rules from `sync_remote` into the local ones. Rules changed on
+/// both sides come back as a `conflict` error and nothing changes, unless
+/// `prefer` (`local` or `remote`) says which side wins.
+#[tauri::command(async)]
+pub fn sync_pull(prefer: Option<sync::Prefer>) -> Result<sync::Pulled, sync::SyncError> {
+    sync::pull(prefer)
+}
+
+/// Sends the local changes to `sync_remote`; `behind` when it has changes
+/// that need a pull first.
+#[tauri::command(async)]
+pub fn sync_push() -> Result<(), sync::SyncError> {
+    sync::push()
+}
+
+#[tauri::command]
+pub fn get_audit_log(filter: Option<audit::Filter>) -> Vec<audit::Record> {
+    audit::query(&filter.unwrap_or_default())
+}
+
+pub const MAIN_WINDOW: &str = "main";
+
+/// Hides the main window, or destroys it when `close_hides_window` is off.
+/// Returns false when there is no main window.
+#[tauri::command]
+pub fn close_main_window(app: AppHandle) -> Result<bool, String> {
+    let Some(win) = app.get_webview_window(MAIN_WINDOW) else {
+        return Ok(false);
+    };
+    if settings::effective().close_hides_window {
+        win.hide()
+    } else {
+        win.destroy()
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Shows, restores and focuses the main window, recreating it from the app
+/// config when it was destroyed.
+pub(crate) fn show_main_window(app: &AppHandle) -> tauri::Result<()> {
+    let win = match app.get_webview_window(MAIN_WINDOW) {
+        Some(win) => win,
+        None => {
+            let config = app
+                .config()
+                .app
+                .windows
+                .iter()
+                .find(|w| w.label == MAIN_WINDOW);
+            let Some(config) = config else {
+                return Ok(());
+            };
+            tauri::WebviewWindowBuilder::from_config(app, config)?.build()?
+        }
+    };
+    win.show()?;
+    win.unminimize()?;
+    win.set_focus()
+}
+
+/// Exits the app once background work has stopped.
+#[tauri::command]
+pub fn quit(app: AppHandle) -> bool {
+    shutdown(&app);
+    app.exit(0);
     true
 }
+
+/// The deep link the UI should navigate to, if one arrived since the last
+/// call; `DEEP_LINK` announces new ones.
+#[tauri::command]
+pub fn take_deep_link() -> Option<deeplink::Link> {
+    deeplink::take_pending()
+}
+
+/// Stops the scheduler and the resolver, commits held-back content edits and
+/// waits for writes in progress.
+/// Safe to call more than once.
+pub(crate) fn shutdown(app: &AppHandle) {
+    if let Some(scheduler) = app.try_state::<scheduler::Scheduler>() {
+        scheduler.stop();
+    }
+    resolver::stop();
+    sync::flush_edits();
+    // every writer holds the store lock, so taking it waits for the last one
+    drop(store::lock());
+}
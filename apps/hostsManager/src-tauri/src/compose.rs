@@ -0,0 +1,581 @@
+// Builds the combined hosts text from the enabled rules in `list.json`.
+//
+// Each enabled rule becomes a block headed by `# --- rule: <title> (<id>) ---`,
+// in tree order. Mappings that repeat an earlier (ip, hostname) pair are
+// removed. When rules map the same hostname to different addresses of one
+// family, the rule with the highest `priority` (default 0) wins and ties go to
+// the rule that comes first; losing mappings are kept as `# overridden by ...`
+// comments, next to what is left of their line. With `ipv6_companions`,
+// loopback and blocking IPv4 entries also get a matching IPv6 entry so
+// IPv6-preferring resolvers cannot bypass them. Every output line is recorded
+// in a source map pointing back to the rule and line it came from.
+//
+// A line of the form `@include <rule-id>` is replaced by the content of that
+// rule (whether or not it is enabled), recursively. Cycles, unknown ids and
+// nesting beyond `MAX_INCLUDE_DEPTH` fail composition with an error that names
+// the rule and line holding the offending directive.
+//
+// Rule content may also reference variables as `${NAME}`. Values come from, in
+// order of precedence: the rule's own `vars`, the `vars` of each enclosing
+// folder (nearest first), and the global `variables.json`. Top-level folders
+// are the app's profiles (see `items::activate_profile`), so a profile's
+// values are the `vars` of its folder. A line that still contains an undefined
+// variable after substitution is commented out rather than written to the
+// system hosts file half-resolved.
+//
+// Wildcard and regex hosts (see `hosts::Pattern`) are replaced by the names in
+// `ComposeOptions::pattern_hosts` that they match, before conflicts are
+// resolved; a line left without hosts is commented out. With `keep_patterns`
+// (for the resolver) they pass through unchanged.
+use crate::flags;
+use crate::hosts::{self, Line, Pattern};
+use crate::tree;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr};
+use std::rc::Rc;
+
+pub type Vars = BTreeMap<String, String>;
+
+/// How many `@include` levels a rule may nest below itself.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    pub rule_id: String,
+    pub title: String,
+    pub line: usize,
+    pub name: String,
+}
+
+/// A broken `@include` directive, located at the line that contains it.
+#[derive(Serialize, Debug, Clone, thiserror::Error)]
+#[error("{title} ({rule_id}), line {line}: {message}")]
+pub struct IncludeError {
+    pub rule_id: String,
+    pub title: String,
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ComposeOptions {
+    /// Emit `::1` / `::` entries next to `127.x` / `0.0.0.0` ones for hostnames
+    /// that have no IPv6 mapping of their own.
+    pub ipv6_companions: bool,
+    /// Leave wildcard and regex hosts in place instead of expanding them.
+    pub keep_patterns: bool,
+    /// The concrete hostnames patterns expand to; filled in from the settings.
+    #[serde(skip)]
+    pub pattern_hosts: Vec<String>,
+}
+
+/// Where a line of the composed output came from. `rule_line` is `None` for
+/// the generated rule header.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    pub line: usize,
+    pub rule_id: String,
+    pub rule_line: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Composed {
+    pub content: String,
+    pub unresolved: Vec<Unresolved>,
+    pub source_map: Vec<SourceMapEntry>,
+}
+
+pub fn is_valid_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+fn item_vars(item: &Value) -> Vars {
+    item.get("vars")
+        .and_then(|v| v.as_object())
+        .map(|m| {
+            m.iter()
+                .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn lookup<'a>(scopes: &'a [Vars], name: &str) -> Option<&'a str> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|s| s.get(name))
+        .map(|s| s.as_str())
+}
+
+/// Replaces every `${NAME}` in `line`. Returns the names that had no value.
+pub fn substitute(line: &str, scopes: &[Vars]) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(line.len());
+    let mut missing = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if is_valid_var_name(&after[..end]) => {
+                let name = &after[..end];
+                match lookup(scopes, name) {
+                    Some(v) => out.push_str(v),
+                    None => {
+                        out.push_str(&rest[start..start + 3 + end]);
+                        missing.push(name.to_string());
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, missing)
+}
+
+// Visits every item with the variable scopes that apply to it; `scopes[0]` is
+// the global scope and the last entry belongs to the item itself.
+fn walk_scoped(
+    items: &[Value],
+    scopes: &mut Vec<Vars>,
+    f: &mut impl FnMut(&Value, &[Vars]) -> Result<(), IncludeError>,
+) -> Result<(), IncludeError> {
+    for item in items {
+        scopes.push(item_vars(item));
+        f(item, scopes)?;
+        if let Some(children) = tree::children(item) {
+            walk_scoped(children, scopes, f)?;
+        }
+        scopes.pop();
+    }
+    Ok(())
+}
+
+// Returns `Some(target)` for an `@include <rule-id>` line; the target is `None`
+// when the directive has no argument.
+fn include_target(line: &str) -> Option<Option<&str>> {
+    let rest = line.trim().strip_prefix("@include")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(
+        rest.split_whitespace()
+            .next()
+            .filter(|t| !t.starts_with('#')),
+    )
+}
+
+// One resolved line and the rule line it came from. For included content the
+// origin is the included rule, not the one that holds the directive.
+struct Fragment {
+    text: String,
+    rule_id: Rc<str>,
+    line: usize,
+}
+
+// The resolved lines of one enabled rule.
+struct Block {
+    id: String,
+    title: String,
+    priority: i64,
+    lines: Vec<Fragment>,
+}
+
+struct Resolver<'a, L> {
+    index: HashMap<&'a str, &'a Value>,
+    load: L,
+    unresolved: Vec<Unresolved>,
+}
+
+impl<'a, L: Fn(&str) -> Option<String>> Resolver<'a, L> {
+    fn new(list: &'a [Value], load: L) -> Self {
+        let mut index = HashMap::new();
+        tree::walk(list, &mut |item| {
+            if let Some(id) = tree::id(item) {
+                index.insert(id, item);
+            }
+        });
+        Resolver {
+            index,
+            load,
+            unresolved: Vec::new(),
+        }
+    }
+
+    // Appends the lines of `content` (the body of rule `id`) to `out`,
+    // expanding includes and variables. `stack` holds the chain of rule ids
+    // being expanded.
+    fn resolve(
+        &mut self,
+        item: &Value,
+        id: &str,
+        content: &str,
+        scopes: &[Vars],
+        stack: &mut Vec<String>,
+        out: &mut Vec<Fragment>,
+    ) -> Result<(), IncludeError> {
+        let rule_id: Rc<str> = Rc::from(id);
+        for (i, line) in content.split('\n').enumerate() {
+            let fragment = |text: String| Fragment {
+                text,
+                rule_id: rule_id.clone(),
+                line: i + 1,
+            };
+
+            // fast path: most lines of large (blocklist) rules need no work
+            if !line.contains("${") && !line.trim_start().starts_with("@include") {
+                out.push(fragment(line.to_string()));
+                continue;
+            }
+
+            if let Some(target) = include_target(line) {
+                let err = |message: String| IncludeError {
+                    rule_id: id.to_string(),
+                    title: tree::title(item).to_string(),
+                    line: i + 1,
+                    message,
+                };
+                let target = target.ok_or_else(|| err("`@include` needs a rule id".to_string()))?;
+                if stack.iter().any(|s| s == target) {
+                    return Err(err(format!(
+                        "include cycle: {} -> {}",
+                        stack.join(" -> "),
+                        target
+                    )));
+                }
+                if stack.len() > MAX_INCLUDE_DEPTH {
+                    return Err(err(format!(
+                        "includes are nested deeper than {} levels",
+                        MAX_INCLUDE_DEPTH
+                    )));
+                }
+                let Some(&target_item) = self.index.get(target) else {
+                    return Err(err(format!("unknown rule id `{}`", target)));
+                };
+                let body = (self.load)(target).unwrap_or_default();
+                stack.push(target.to_string());
+                self.resolve(target_item, target, &body, scopes, stack, out)?;
+                stack.pop();
+                continue;
+            }
+
+            let (resolved, missing) = substitute(line, scopes);
+            if missing.is_empty() {
+                out.push(fragment(resolved));
+                continue;
+            }
+            out.push(fragment(format!(
+                "# unresolved ${{{}}}: {}",
+                missing.join("}, ${"),
+                line
+            )));
+            self.unresolved
+                .extend(missing.into_iter().map(|name| Unresolved {
+                    rule_id: id.to_string(),
+                    title: tree::title(item).to_string(),
+                    line: i + 1,
+                    name,
+                }));
+        }
+        Ok(())
+    }
+}
+
+// Replaces pattern hosts with the matching `names`, in the order of `names`.
+fn expand_patterns(blocks: &mut [Block], names: &[String]) {
+    let mut cache: HashMap<String, Option<Pattern>> = HashMap::new();
+    for fragment in blocks.iter_mut().flat_map(|b| b.lines.iter_mut()) {
+        // fast path: no pattern syntax on the line
+        if !fragment.text.contains(['*', '/']) {
+            continue;
+        }
+        let Line::Entry { ip_text, hosts, .. } = hosts::parse_line(&fragment.text) else {
+            continue;
+        };
+        if !hosts.iter().any(|h| hosts::is_pattern(h)) {
+            continue;
+        }
+        let mut expanded: Vec<&str> = Vec::new();
+        for host in &hosts {
+            if !hosts::is_pattern(host) {
+                expanded.push(host);
+                continue;
+            }
+            let pattern = cache
+                .entry(host.to_string())
+                .or_insert_with(|| Pattern::new(host).ok());
+            if let Some(pattern) = pattern {
+                expanded.extend(
+                    names
+                        .iter()
+                        .map(|n| n.as_str())
+                        .filter(|n| pattern.matches(n)),
+                );
+            }
+        }
+        let mut seen = HashSet::new();
+        expanded.retain(|h| seen.insert(h.to_ascii_lowercase()));
+        fragment.text = if expanded.is_empty() {
+            format!("# no pattern hosts match: {}", fragment.text.trim())
+        } else {
+            format!("{} {}", ip_text, expanded.join(" "))
+        };
+    }
+}
+
+fn priority(item: &Value) -> i64 {
+    item.get("priority").and_then(|v| v.as_i64()).unwrap_or(0)
+}
+
+// A hostname claimed by an IP of one address family; v4 and v6 mappings of the
+// same name never conflict.
+type HostKey = (String, bool);
+
+struct Winner {
+    ip: IpAddr,
+    priority: i64,
+    block: usize,
+}
+
+// For every hostname, the mapping that survives: highest rule priority first,
+// then earliest in tree order (the first match wins in a hosts file).
+fn pick_winners(blocks: &[Block]) -> HashMap<HostKey, Winner> {
+    let mut winners: HashMap<HostKey, Winner> = HashMap::new();
+    for (b, block) in blocks.iter().enumerate() {
+        for fragment in &block.lines {
+            let Line::Entry { ip, hosts, .. } = hosts::parse_line(&fragment.text) else {
+                continue;
+            };
+            for host in hosts {
+                let key = (host.to_ascii_lowercase(), ip.is_ipv4());
+                match winners.get(&key) {
+                    Some(w) if w.priority >= block.priority => {}
+                    _ => {
+                        winners.insert(
+                            key,
+                            Winner {
+                                ip,
+                                priority: block.priority,
+                                block: b,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+    winners
+}
+
+// The IPv6 address with the same meaning as a loopback or blocking IPv4 one.
+fn ipv6_companion(ip: &IpAddr) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(v4) if v4.is_loopback() => Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        IpAddr::V4(v4) if v4.is_unspecified() => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        _ => None,
+    }
+}
+
+fn emit(blocks: &[Block], unresolved: Vec<Unresolved>, opts: &ComposeOptions) -> Composed {
+    let winners = pick_winners(blocks);
+    let size: usize = blocks
+        .iter()
+        .flat_map(|b| &b.lines)
+        .map(|f| f.text.len() + 1)
+        .sum();
+    let mut content = String::with_capacity(size + blocks.len() * 64);
+    let mut source_map = Vec::new();
+    let mut seen: HashSet<(IpAddr, String)> = HashSet::new();
+    let mut line_no = 0;
+    let mut push = |text: &str, source: Option<(&str, Option<usize>)>| {
+        content.push_str(text);
+        content.push('\n');
+        line_no += 1;
+        if let Some((rule_id, rule_line)) = source {
+            source_map.push(SourceMapEntry {
+                line: line_no,
+                rule_id: rule_id.to_string(),
+                rule_line,
+            });
+        }
+    };
+
+    for (b, block) in blocks.iter().enumerate() {
+        if b > 0 {
+            push("", None);
+        }
+        let header = format!("# --- rule: {} ({}) ---", block.title, block.id);
+        push(&header, Some((&block.id, None)));
+
+        let mut lines = &block.lines[..];
+        while let [rest @ .., last] = lines {
+            if !last.text.trim().is_empty() {
+                break;
+            }
+            lines = rest;
+        }
+
+        for fragment in lines {
+            let source = Some((&*fragment.rule_id, Some(fragment.line)));
+            let Line::Entry { ip, ip_text, hosts } = hosts::parse_line(&fragment.text) else {
+                push(&fragment.text, source);
+                continue;
+            };
+
+            // winning block -> the hosts of this line it takes
+            let mut overridden: Vec<(usize, Vec<&str>)> = Vec::new();
+            let kept: Vec<&str> = hosts
+                .iter()
+                .copied()
+                .filter(|h| {
+                    let host = h.to_ascii_lowercase();
+                    match winners.get(&(host.clone(), ip.is_ipv4())) {
+                        Some(w) if w.ip != ip => {
+                            match overridden.iter_mut().find(|(b, _)| *b == w.block) {
+                                Some((_, dropped)) => dropped.push(h),
+                                None => overridden.push((w.block, vec![h])),
+                            }
+                            false
+                        }
+                        _ => seen.insert((ip, host)),
+                    }
+                })
+                .collect();
+
+            if kept.len() == hosts.len() {
+                push(&fragment.text, source);
+            } else if let ([], [(w, _)]) = (&kept[..], &overridden[..]) {
+                let winner = &blocks[*w];
+                let text = format!(
+                    "# overridden by {} ({}): {}",
+                    winner.title,
+                    winner.id,
+                    fragment.text.trim()
+                );
+                push(&text, source);
+            } else {
+                // what is left of the line keeps its comment, and each winner
+                // gets a note naming the hosts it took
+                let mut comment = fragment.text.find('#').map(|i| fragment.text[i..].trim());
+                if !kept.is_empty() {
+                    let text = match comment.take() {
+                        Some(c) => format!("{} {} {}", ip_text, kept.join(" "), c),
+                        None => format!("{} {}", ip_text, kept.join(" ")),
+                    };
+                    push(&text, source);
+                }
+                for (w, dropped) in &overridden {
+                    let winner = &blocks[*w];
+                    let mut text = format!(
+                        "# overridden by {} ({}): {} {}",
+                        winner.title,
+                        winner.id,
+                        ip_text,
+                        dropped.join(" ")
+                    );
+                    if let Some(c) = comment.take() {
+                        text = format!("{} {}", text, c);
+                    }
+                    push(&text, source);
+                }
+            }
+            // a line whose mappings are all exact duplicates is dropped
+
+            if let Some(v6) = opts.ipv6_companions.then(|| ipv6_companion(&ip)).flatten() {
+                let missing: Vec<&str> = kept
+                    .iter()
+                    .copied()
+                    .filter(|h| {
+                        let host = h.to_ascii_lowercase();
+                        !winners.contains_key(&(host.clone(), false)) && seen.insert((v6, host))
+                    })
+                    .collect();
+                if !missing.is_empty() {
+                    push(&format!("{} {}", v6, missing.join(" ")), source);
+                }
+            }
+        }
+    }
+
+    Composed {
+        content,
+        unresolved,
+        source_map,
+    }
+}
+
+/// Composes the content of every enabled or pinned rule in tree order;
+/// `system` rules are left out.
+///
+/// Included rules are expanded with the variable scopes of the including rule
+/// and take its priority.
+pub fn compose(
+    list: &[Value],
+    globals: &Vars,
+    opts: &ComposeOptions,
+    load: impl Fn(&str) -> Option<String>,
+) -> Result<Composed, IncludeError> {
+    let mut resolver = Resolver::new(list, load);
+    let mut blocks = Vec::new();
+    let mut scopes = vec![globals.clone()];
+    walk_scoped(list, &mut scopes, &mut |item, scopes| {
+        if !flags::is_active(item) {
+            return Ok(());
+        }
+        let Some(id) = tree::id(item) else {
+            return Ok(());
+        };
+        let Some(content) = (resolver.load)(id) else {
+            return Ok(());
+        };
+        let mut lines = Vec::new();
+        let mut stack = vec![id.to_string()];
+        resolver.resolve(item, id, &content, scopes, &mut stack, &mut lines)?;
+        blocks.push(Block {
+            id: id.to_string(),
+            title: tree::title(item).to_string(),
+            priority: priority(item),
+            lines,
+        });
+        Ok(())
+    })?;
+    if !opts.keep_patterns {
+        expand_patterns(&mut blocks, &opts.pattern_hosts);
+    }
+    Ok(emit(&blocks, resolver.unresolved, opts))
+}
+
+/// Lists undefined variable references in every rule, enabled or not. Rules
+/// with broken includes are checked up to the failing directive.
+pub fn check_variables(
+    list: &[Value],
+    globals: &Vars,
+    load: impl Fn(&str) -> Option<String>,
+) -> Vec<Unresolved> {
+    let mut resolver = Resolver::new(list, load);
+    let mut scopes = vec![globals.clone()];
+    let _ = walk_scoped(list, &mut scopes, &mut |item, scopes| {
+        let Some(id) = tree::id(item) else {
+            return Ok(());
+        };
+        if let Some(content) = (resolver.load)(id) {
+            let mut stack = vec![id.to_string()];
+            let _ = resolver.resolve(item, id, &content, scopes, &mut stack, &mut Vec::new());
+        }
+        Ok(())
+    });
+    resolver.unresolved
+}
@@ -0,0 +1,239 @@
+// Deep links: `hostsmanager://<action>?<args>` and the suite-wide
+// `tlfsuite://open?app=hostsManager&args=<encoded query>`.
+//
+// The launcher opens `hostsmanager://open?args=<encoded query>`, with the
+// action named by an `action` argument inside `args` (`tlfsuite.json` opts in
+// with `actionArg`); a plain `open` is the default. Whatever the shape, the
+// action and its arguments are checked against the actions declared in
+// `tlfsuite.json`, so the launcher and this router agree on one list.
+//
+// Links that change rules (`enableRule`, `disableRule`, `apply`) run here
+// without the UI, like the tray. Links that navigate, and `import`, which the
+// user confirms after a preview, are parked until the UI takes them
+// (`take_deep_link`) and announced with `DEEP_LINK`; the UI may not be
+// listening yet when the app was started by the link.
+use crate::{commands, share, tray, tree};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use tauri::{AppHandle, Emitter};
+use thiserror::Error;
+use url::Url;
+
+const SUITE_SCHEME: &str = "tlfsuite";
+
+#[derive(Deserialize)]
+struct Manifest {
+    id: String,
+    scheme: String,
+    actions: Vec<ActionSpec>,
+}
+
+#[derive(Deserialize)]
+struct ActionSpec {
+    name: String,
+    #[serde(default)]
+    args: Vec<ArgSpec>,
+}
+
+#[derive(Deserialize)]
+struct ArgSpec {
+    name: String,
+    #[serde(default)]
+    required: bool,
+}
+
+fn manifest() -> &'static Manifest {
+    static MANIFEST: OnceLock<Manifest> = OnceLock::new();
+    MANIFEST.get_or_init(|| {
+        serde_json::from_str(include_str!("../tlfsuite.json")).expect("tlfsuite.json is valid")
+    })
+}
+
+/// A validated link; the tag is the action name from `tlfsuite.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum Link {
+    Open,
+    OpenRuleById {
+        id: String,
+    },
+    OpenRuleByName {
+        name: String,
+    },
+    EnableRule {
+        id: String,
+    },
+    DisableRule {
+        id: String,
+    },
+    Apply,
+    /// A rule shared with `share_rule`; `data` is its payload.
+    Import {
+        data: String,
+    },
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("not a valid link: {0}")]
+    Malformed(String),
+    #[error("link is not for this app: {0}")]
+    Foreign(String),
+    #[error("unknown action {0}")]
+    UnknownAction(String),
+    #[error("{action} needs the argument {arg}")]
+    MissingArg { action: String, arg: String },
+    #[error("{action} takes no argument {arg}")]
+    UnexpectedArg { action: String, arg: String },
+}
+
+/// The URL scheme of this app's links.
+pub fn scheme() -> &'static str {
+    &manifest().scheme
+}
+
+// Query pairs of `url` with the ones inside `args` spliced in.
+fn query_args(url: &Url) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    for (k, v) in url.query_pairs() {
+        if k == "args" {
+            out.extend(
+                url::form_urlencoded::parse(v.as_bytes()).map(|(k, v)| (k.into(), v.into())),
+            );
+        } else {
+            out.push((k.into(), v.into()));
+        }
+    }
+    out
+}
+
+/// Parses and validates a deep link URL.
+pub fn parse(link: &str) -> Result<Link, LinkError> {
+    let url = Url::parse(link.trim()).map_err(|e| LinkError::Malformed(e.to_string()))?;
+    let manifest = manifest();
+    let host = url.host_str().unwrap_or("");
+    let mut args = query_args(&url);
+
+    let mut action = if url.scheme().eq_ignore_ascii_case(&manifest.scheme) {
+        host.to_string()
+    } else if url.scheme().eq_ignore_ascii_case(SUITE_SCHEME) && host == "open" {
+        let app = args.iter().position(|(k, _)| k == "app");
+        match app.map(|i| args.remove(i).1) {
+            Some(app) if app.eq_ignore_ascii_case(&manifest.id) => "open".to_string(),
+            _ => return Err(LinkError::Foreign(link.to_string())),
+        }
+    } else {
+        return Err(LinkError::Foreign(link.to_string()));
+    };
+    if let Some(i) = args.iter().position(|(k, _)| k == "action") {
+        action = args.remove(i).1;
+    }
+
+    let spec = manifest
+        .actions
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(&action))
+        .ok_or_else(|| LinkError::UnknownAction(action.clone()))?;
+    let mut fields = Map::new();
+    fields.insert("action".to_string(), Value::String(spec.name.clone()));
+    for (k, v) in args {
+        if !spec.args.iter().any(|a| a.name == k) {
+            return Err(LinkError::UnexpectedArg {
+                action: spec.name.clone(),
+                arg: k,
+            });
+        }
+        fields.insert(k, Value::String(v));
+    }
+    for arg in spec.args.iter().filter(|a| a.required) {
+        let given = fields.get(&arg.name).and_then(|v| v.as_str());
+        if given.is_none_or(|v| v.trim().is_empty()) {
+            return Err(LinkError::MissingArg {
+                action: spec.name.clone(),
+                arg: arg.name.clone(),
+            });
+        }
+    }
+    // declared but not routed here: the manifest and `Link` disagree
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|_| LinkError::UnknownAction(spec.name.clone()))
+}
+
+/// Every URL in a second instance's command line that is a link for this
+/// app; other arguments are ignored.
+pub fn links_in_args(args: &[String]) -> Vec<String> {
+    args.iter()
+        .skip(1)
+        .filter(|a| {
+            !matches!(
+                parse(a),
+                Err(LinkError::Foreign(_) | LinkError::Malformed(_))
+            )
+        })
+        .cloned()
+        .collect()
+}
+
+static PENDING: Mutex<Option<Link>> = Mutex::new(None);
+
+/// The last navigation link the UI has not taken yet.
+pub fn take_pending() -> Option<Link> {
+    PENDING.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+fn show(app: &AppHandle, link: Link) {
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(link.clone());
+    let _ = commands::show_main_window(app);
+    let _ = app.emit("DEEP_LINK", link);
+}
+
+/// Runs a deep link; problems are reported as notifications since the window
+/// may be hidden.
+pub fn handle(app: &AppHandle, link: &str) {
+    let link = match parse(link) {
+        Ok(link) => link,
+        Err(e) => {
+            tray::notify_error(app, &format!("无法打开链接：{}", e));
+            return;
+        }
+    };
+    let app = app.clone();
+    match link {
+        Link::Open => {
+            let _ = commands::show_main_window(&app);
+        }
+        Link::OpenRuleById { .. } => show(&app, link),
+        Link::OpenRuleByName { name } => {
+            let mut found = None;
+            tree::walk(&commands::get_list(), &mut |item| {
+                if found.is_none() && tree::title(item) == name {
+                    found = tree::id(item).map(String::from);
+                }
+            });
+            match found {
+                Some(id) => show(&app, Link::OpenRuleById { id }),
+                None => {
+                    tray::notify_error(&app, &format!("未找到规则：{}", name));
+                    let _ = commands::show_main_window(&app);
+                }
+            }
+        }
+        // writes may wait on the store lock
+        Link::EnableRule { id } => {
+            thread::spawn(move || tray::set_on(&app, &id, true));
+        }
+        Link::DisableRule { id } => {
+            thread::spawn(move || tray::set_on(&app, &id, false));
+        }
+        Link::Apply => {
+            thread::spawn(move || tray::apply_now(&app));
+        }
+        // a damaged payload is reported here; the UI only sees good ones
+        Link::Import { ref data } => match share::decode(data) {
+            Ok(_) => show(&app, link),
+            Err(e) => tray::notify_error(&app, &format!("无法导入规则：{}", e)),
+        },
+    }
+}
@@ -0,0 +1,92 @@
+// Conversion of the composed rules into DNS server configuration.
+//
+// Input is what would be written to the system hosts file, so overrides,
+// includes and variables are already resolved. Every (hostname, address) pair
+// is exported once, in the order it first appears; hostnames are lowercased.
+// Names a DNS server cannot serve (anything that is not a plain domain, such as
+// `foo bar` or `*.test`) are left out.
+//
+// Note that dnsmasq's `address=/host/ip` also answers for every subdomain of
+// `host`; the other formats map exactly the listed names.
+use crate::blocklist;
+use crate::hosts::{self, Line};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dnsmasq,
+    Unbound,
+    Coredns,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dnsmasq" => Ok(ExportFormat::Dnsmasq),
+            "unbound" => Ok(ExportFormat::Unbound),
+            "coredns" => Ok(ExportFormat::Coredns),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("unknown export format: {}", s)),
+        }
+    }
+}
+
+/// The (hostname, address) pairs of hosts `content`, without repeats.
+pub fn mappings(content: &str) -> Vec<(String, IpAddr)> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for (_, raw) in hosts::lines(content) {
+        let Line::Entry { ip, hosts, .. } = hosts::parse_line(raw) else {
+            continue;
+        };
+        for host in hosts {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            if blocklist::is_valid_domain(&host) && seen.insert((host.clone(), ip)) {
+                out.push((host, ip));
+            }
+        }
+    }
+    out
+}
+
+pub fn render(content: &str, format: ExportFormat) -> String {
+    let pairs = mappings(content);
+    let mut out = String::new();
+    match format {
+        ExportFormat::Dnsmasq => {
+            for (host, ip) in &pairs {
+                let _ = writeln!(out, "address=/{}/{}", host, ip);
+            }
+        }
+        ExportFormat::Unbound => {
+            out.push_str("server:\n");
+            for (host, ip) in &pairs {
+                let kind = if ip.is_ipv4() { "A" } else { "AAAA" };
+                let _ = writeln!(out, "    local-data: \"{}. IN {} {}\"", host, kind, ip);
+            }
+        }
+        ExportFormat::Coredns => {
+            // names missing here are passed on to the next plugin
+            out.push_str("hosts {\n");
+            for (host, ip) in &pairs {
+                let _ = writeln!(out, "    {} {}", ip, host);
+            }
+            out.push_str("    fallthrough\n}\n");
+        }
+        ExportFormat::Json => {
+            let mut map: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+            for (host, ip) in &pairs {
+                map.entry(host).or_default().push(ip.to_string());
+            }
+            out = serde_json::to_string_pretty(&map).unwrap_or_default();
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,152 @@
+// Rule flags enforced by the backend.
+//
+// - `system`: the rule mirrors the system hosts file. Its content is read from
+//   that file and cannot be written through the rule, it is left out of
+//   composition (which would copy the file into itself), it cannot be deleted,
+//   and the flag cannot be taken off again. Nor can it be put on: system rules
+//   only come from a `list.json` written outside the app, and `items` strips
+//   the flag from created and duplicated rules.
+// - `locked`: the rule's content is read-only; edits, imports and refreshes
+//   are refused until the flag is cleared.
+// - `pinned`: the rule is always composed and cannot be switched off;
+//   schedules leave it alone.
+//
+// `store` checks every list write against the list it replaces, so a
+// violation is rejected before anything reaches the disk. A violation that is
+// already stored (say, a hand-edited `list.json`) does not block other edits.
+use crate::tree;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FlagError {
+    #[error("{title} mirrors the system hosts file and cannot be deleted")]
+    SystemDeleted { id: String, title: String },
+    #[error("{title} mirrors the system hosts file; its system flag cannot change")]
+    SystemFlag { id: String, title: String },
+    #[error("{title} mirrors the system hosts file; edit the system hosts instead")]
+    SystemContent { id: String, title: String },
+    #[error("{title} is locked; unlock it to change its content")]
+    Locked { id: String, title: String },
+    #[error("{title} is pinned and cannot be switched off")]
+    Pinned { id: String, title: String },
+}
+
+impl FlagError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FlagError::SystemDeleted { .. }
+            | FlagError::SystemFlag { .. }
+            | FlagError::SystemContent { .. } => "system",
+            FlagError::Locked { .. } => "locked",
+            FlagError::Pinned { .. } => "pinned",
+        }
+    }
+
+    pub fn rule_id(&self) -> &str {
+        match self {
+            FlagError::SystemDeleted { id, .. }
+            | FlagError::SystemFlag { id, .. }
+            | FlagError::SystemContent { id, .. }
+            | FlagError::Locked { id, .. }
+            | FlagError::Pinned { id, .. } => id,
+        }
+    }
+}
+
+// Sent to the UI as `{ code, message, rule_id }`.
+impl Serialize for FlagError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("FlagError", 3)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("rule_id", self.rule_id())?;
+        s.end()
+    }
+}
+
+fn flag(item: &Value, name: &str) -> bool {
+    item.get(name).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+pub fn is_system(item: &Value) -> bool {
+    flag(item, "system")
+}
+
+pub fn is_locked(item: &Value) -> bool {
+    flag(item, "locked")
+}
+
+pub fn is_pinned(item: &Value) -> bool {
+    flag(item, "pinned")
+}
+
+/// Whether the rule takes part in composition.
+pub fn is_active(item: &Value) -> bool {
+    (tree::is_on(item) || is_pinned(item)) && !is_system(item)
+}
+
+fn id_and_title(item: &Value) -> (String, String) {
+    (
+        tree::id(item).unwrap_or_default().to_string(),
+        tree::title(item).to_string(),
+    )
+}
+
+// Every item of the tree in tree order, and the same by id.
+fn flatten(list: &[Value]) -> (Vec<&Value>, HashMap<&str, &Value>) {
+    let mut items = Vec::new();
+    tree::walk(list, &mut |item| items.push(item));
+    let by_id = items
+        .iter()
+        .filter_map(|item| Some((tree::id(item)?, *item)))
+        .collect();
+    (items, by_id)
+}
+
+/// Checks that replacing the rule tree `before` with `after` respects the
+/// flags of every rule.
+pub fn check_list(before: &[Value], after: &[Value]) -> Result<(), FlagError> {
+    let (old_items, old) = flatten(before);
+    let (new_items, new) = flatten(after);
+    for item in old_items.into_iter().filter(|item| is_system(item)) {
+        let (id, title) = id_and_title(item);
+        match new.get(id.as_str()) {
+            None => return Err(FlagError::SystemDeleted { id, title }),
+            Some(now) if !is_system(now) => return Err(FlagError::SystemFlag { id, title }),
+            _ => {}
+        }
+    }
+    let switched_off = |v: &Value| is_pinned(v) && !tree::is_on(v);
+    for item in new_items {
+        let prev = tree::id(item).and_then(|id| old.get(id));
+        if is_system(item) && !prev.is_some_and(|p| is_system(p)) {
+            let (id, title) = id_and_title(item);
+            return Err(FlagError::SystemFlag { id, title });
+        }
+        if switched_off(item) && !prev.is_some_and(|p| switched_off(p)) {
+            let (id, title) = id_and_title(item);
+            return Err(FlagError::Pinned { id, title });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the content of rule `id` may be written. Unknown ids are
+/// allowed; content can be written before its rule is saved.
+pub fn check_content(list: &[Value], id: &str) -> Result<(), FlagError> {
+    let Some(item) = tree::find(list, id) else {
+        return Ok(());
+    };
+    let (id, title) = id_and_title(item);
+    if is_system(item) {
+        return Err(FlagError::SystemContent { id, title });
+    }
+    if is_locked(item) {
+        return Err(FlagError::Locked { id, title });
+    }
+    Ok(())
+}
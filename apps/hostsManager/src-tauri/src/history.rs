@@ -0,0 +1,81 @@
+// Previous versions of the system hosts file, kept for rollback.
+//
+// Before a write replaces the system hosts file, the content being replaced is
+// saved as `history/<time_ms>.hosts` in the data directory. Only the newest
+// `history_retention` versions are kept.
+use crate::commands;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Entry {
+    pub id: String,
+    pub time_ms: u64,
+    pub size: u64,
+}
+
+fn history_dir() -> PathBuf {
+    commands::data_dir().join("history")
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Saves `old_content` as the newest version and drops versions beyond
+/// `retention`.
+pub fn save(old_content: &str, retention: usize) -> io::Result<()> {
+    if retention == 0 {
+        return Ok(());
+    }
+    let dir = history_dir();
+    fs::create_dir_all(&dir)?;
+    let mut time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    // keep ids unique when several writes land in the same millisecond
+    while dir.join(format!("{}.hosts", time_ms)).exists() {
+        time_ms += 1;
+    }
+    fs::write(dir.join(format!("{}.hosts", time_ms)), old_content)?;
+
+    for stale in list().into_iter().skip(retention) {
+        let _ = fs::remove_file(dir.join(format!("{}.hosts", stale.id)));
+    }
+    Ok(())
+}
+
+/// All saved versions, newest first.
+pub fn list() -> Vec<Entry> {
+    let Ok(entries) = fs::read_dir(history_dir()) else {
+        return vec![];
+    };
+    let mut out: Vec<Entry> = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let id = name.strip_suffix(".hosts")?;
+            if !is_valid_id(id) {
+                return None;
+            }
+            Some(Entry {
+                id: id.to_string(),
+                time_ms: id.parse().ok()?,
+                size: e.metadata().map(|m| m.len()).unwrap_or(0),
+            })
+        })
+        .collect();
+    out.sort_by_key(|e| std::cmp::Reverse(e.time_ms));
+    out
+}
+
+pub fn read(id: &str) -> Option<String> {
+    if !is_valid_id(id) {
+        return None;
+    }
+    fs::read_to_string(history_dir().join(format!("{}.hosts", id))).ok()
+}
@@ -0,0 +1,112 @@
+// Line-level parser for hosts file content.
+//
+// Shared by search, composition and validation so every feature agrees on what
+// counts as an entry. A line is `IP host [host ...] [# comment]`; anything that
+// does not start with a parseable IP address is reported as `Invalid`.
+//
+// A host may also be a pattern, which no system resolver understands: a
+// wildcard such as `*.preview.internal` or `api-*.test`, where `*` stands for
+// one or more characters (dots included), or a regex between slashes such as
+// `/^api-[0-9]+[.]test$/`, which must match the whole name. Both ignore case.
+// Patterns are expanded against a list of concrete names when composing for the
+// hosts file and served as they are by the resolver. A regex cannot contain
+// whitespace or `#`, which end the token.
+use regex::{Regex, RegexBuilder};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line<'a> {
+    Blank,
+    Comment(&'a str),
+    Entry {
+        ip: IpAddr,
+        ip_text: &'a str,
+        hosts: Vec<&'a str>,
+    },
+    Invalid(&'a str),
+}
+
+pub fn parse_line(raw: &str) -> Line<'_> {
+    let line = raw.trim();
+    if line.is_empty() {
+        return Line::Blank;
+    }
+    if let Some(c) = line.strip_prefix('#') {
+        return Line::Comment(c.trim());
+    }
+
+    let body = match line.find('#') {
+        Some(i) => &line[..i],
+        None => line,
+    };
+    let mut tokens = body.split_whitespace();
+    let ip_text = match tokens.next() {
+        Some(t) => t,
+        None => return Line::Invalid(line),
+    };
+    let hosts: Vec<&str> = tokens.collect();
+    match parse_ip(ip_text) {
+        Some(ip) if !hosts.is_empty() => Line::Entry { ip, ip_text, hosts },
+        _ => Line::Invalid(line),
+    }
+}
+
+/// Parses an address as it appears in a hosts file, tolerating an IPv6 zone
+/// suffix such as `fe80::1%lo0`.
+pub fn parse_ip(s: &str) -> Option<IpAddr> {
+    let addr = s.split('%').next().unwrap_or(s);
+    addr.parse().ok()
+}
+
+/// Iterates `(line_number, line)` pairs with 1-based line numbers.
+pub fn lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content.lines().enumerate().map(|(i, l)| (i + 1, l))
+}
+
+pub fn is_pattern(host: &str) -> bool {
+    host.contains('*') || is_regex(host)
+}
+
+fn is_regex(host: &str) -> bool {
+    host.len() > 2 && host.starts_with('/') && host.ends_with('/')
+}
+
+/// A compiled wildcard or regex host.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub text: String,
+    re: Regex,
+    // literal characters of a wildcard; 0 for regexes
+    literal_len: usize,
+}
+
+impl Pattern {
+    pub fn new(host: &str) -> Result<Pattern, String> {
+        let (source, literal_len) = if is_regex(host) {
+            (format!("^(?:{})$", &host[1..host.len() - 1]), 0)
+        } else {
+            let parts: Vec<&str> = host.split('*').collect();
+            let escaped: Vec<String> = parts.iter().map(|p| regex::escape(p)).collect();
+            (format!("^{}$", escaped.join(".+")), parts.concat().len())
+        };
+        let re = RegexBuilder::new(&source)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("invalid pattern {}: {}", host, e))?;
+        Ok(Pattern {
+            text: host.to_string(),
+            re,
+            literal_len,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.re.is_match(name)
+    }
+
+    /// Orders patterns so the more specific one is tried first: wildcards by
+    /// their literal length, then regexes.
+    pub fn specificity(&self) -> usize {
+        self.literal_len
+    }
+}
@@ -0,0 +1,364 @@
+// Granular edits of the rule tree.
+//
+// Each operation is a single read-modify-write of `list.json` under the store
+// lock, so it cannot interleave with another writer. Content files follow the
+// tree: a new rule may start with content, a duplicate gets copies of every
+// file below it, and a delete removes the files of the whole subtree. With
+// sync on, each operation is committed once it is saved.
+//
+// Top-level folders double as profiles (a "staging" folder, say; see
+// `compose` for their variables): activating one switches on the rules below
+// it and switches off those below the other top-level folders.
+use crate::store::{self, StoreError};
+use crate::{commands, flags, sync, tree};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum ItemError {
+    #[error("no rule with id {0}")]
+    NotFound(String),
+    #[error("{0} is not a folder")]
+    NotAFolder(String),
+    #[error("cannot move {0} into itself")]
+    IntoItself(String),
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl From<io::Error> for ItemError {
+    fn from(e: io::Error) -> Self {
+        ItemError::Store(StoreError::Io(e))
+    }
+}
+
+impl ItemError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ItemError::NotFound(_) => "not_found",
+            ItemError::NotAFolder(_) => "not_a_folder",
+            ItemError::IntoItself(_) => "into_itself",
+            ItemError::Invalid(_) => "invalid",
+            ItemError::Store(StoreError::Conflict { .. }) => "conflict",
+            ItemError::Store(StoreError::Flag(e)) => e.code(),
+            ItemError::Store(StoreError::Io(_)) => "io",
+        }
+    }
+}
+
+impl Serialize for ItemError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ItemError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn new_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+// The children of folder `parent`, or the top level for `None`.
+fn container<'a>(
+    list: &'a mut Vec<Value>,
+    parent: Option<&str>,
+) -> Result<&'a mut Vec<Value>, ItemError> {
+    let Some(pid) = parent else {
+        return Ok(list);
+    };
+    let item = tree::find_mut(list, pid).ok_or_else(|| ItemError::NotFound(pid.to_string()))?;
+    if !tree::is_folder(item) {
+        return Err(ItemError::NotAFolder(pid.to_string()));
+    }
+    item.as_object_mut()
+        .and_then(|obj| {
+            obj.entry("children")
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+        })
+        .ok_or_else(|| ItemError::Invalid(format!("{} has malformed children", pid)))
+}
+
+// The sibling list holding `id` and its position in it.
+fn locate<'a>(items: &'a mut Vec<Value>, id: &str) -> Option<(&'a mut Vec<Value>, usize)> {
+    if let Some(i) = items.iter().position(|v| tree::id(v) == Some(id)) {
+        return Some((items, i));
+    }
+    items
+        .iter_mut()
+        .filter_map(tree::children_mut)
+        .find_map(|children| locate(children, id))
+}
+
+fn insert(items: &mut Vec<Value>, index: Option<usize>, item: Value) {
+    let i = index.unwrap_or(items.len()).min(items.len());
+    items.insert(i, item);
+}
+
+// Gives `item` and everything below it fresh ids, recording old -> new. Copies
+// never mirror the system hosts file, so `system` is dropped as well.
+fn reassign_ids(item: &mut Value, now: u64, ids: &mut Vec<(String, String)>) {
+    if let Some(obj) = item.as_object_mut() {
+        obj.remove("system");
+        let new = new_id();
+        if let Some(old) = obj.get("id").and_then(|v| v.as_str()) {
+            ids.push((old.to_string(), new.clone()));
+        }
+        obj.insert("id".to_string(), json!(new));
+        obj.insert("add_time_ms".to_string(), json!(now));
+    }
+    if let Some(children) = tree::children_mut(item) {
+        for child in children {
+            reassign_ids(child, now, ids);
+        }
+    }
+}
+
+/// Adds `item` under `parent` (top level for `None`) at `index` (the end for
+/// `None`). `id` and `add_time_ms` are always assigned here; `type` defaults to
+/// `file`. Returns the stored item.
+pub fn create(
+    parent: Option<&str>,
+    item: Value,
+    index: Option<usize>,
+    content: Option<&str>,
+) -> Result<Value, ItemError> {
+    let mut obj = match item {
+        Value::Object(obj) => obj,
+        Value::Null => Map::new(),
+        _ => return Err(ItemError::Invalid("item must be an object".to_string())),
+    };
+    let id = new_id();
+    obj.insert("id".to_string(), json!(id));
+    obj.insert("add_time_ms".to_string(), json!(now_ms()));
+    obj.entry("type").or_insert_with(|| json!("file"));
+    // only a rule already stored can mirror the system hosts file
+    obj.remove("system");
+    // a pinned rule is on from the start
+    if obj.get("pinned").and_then(|v| v.as_bool()) == Some(true) {
+        obj.insert("on".to_string(), json!(true));
+    }
+    // children are created one by one so every one of them gets an id
+    obj.remove("children");
+    let mut item = Value::Object(obj);
+    if tree::is_folder(&item) {
+        if content.is_some() {
+            return Err(ItemError::Invalid("folders have no content".to_string()));
+        }
+        item["children"] = json!([]);
+    }
+
+    let stored = store::update_list(|list| {
+        let target = container(list, parent)?;
+        if let Some(content) = content {
+            store::write_atomic(&commands::content_path(&id), content.as_bytes())?;
+        }
+        insert(target, index, item.clone());
+        Ok::<_, ItemError>(item)
+    });
+    // the list may still be rejected (say, by its flags) after the content is
+    // written; the id is new, so the file belongs to nothing else
+    if stored.is_err() && content.is_some() {
+        let _ = fs::remove_file(commands::content_path(&id));
+    }
+    let item = stored?;
+    sync::record(&format!("Add {}", sync::label(&item)));
+    Ok(item)
+}
+
+/// Merges `patch` into the item: a `null` value removes the field. `id`,
+/// `children` and `add_time_ms` are managed here and cannot be patched.
+pub fn update(id: &str, patch: Map<String, Value>) -> Result<Value, ItemError> {
+    for key in ["id", "children", "add_time_ms"] {
+        if patch.contains_key(key) {
+            return Err(ItemError::Invalid(format!("{} cannot be changed", key)));
+        }
+    }
+    let (before, after) = store::update_list(|list| {
+        let item = tree::find_mut(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        let before = item.clone();
+        let obj = item
+            .as_object_mut()
+            .ok_or_else(|| ItemError::Invalid(format!("{} is malformed", id)))?;
+        for (k, v) in patch {
+            if v.is_null() {
+                obj.remove(&k);
+            } else {
+                obj.insert(k, v);
+            }
+        }
+        if tree::is_folder(item) {
+            if tree::children(item).is_none() {
+                item["children"] = json!([]);
+            }
+        } else if let Some(obj) = item.as_object_mut() {
+            match obj.get("children").and_then(|c| c.as_array()) {
+                Some(c) if !c.is_empty() => {
+                    return Err(ItemError::Invalid(format!(
+                        "{} still has children and must stay a folder",
+                        id
+                    )))
+                }
+                _ => {
+                    obj.remove("children");
+                }
+            }
+        }
+        Ok::<_, ItemError>((before, item.clone()))
+    })?;
+    sync::record(&sync::describe_edit(&before, &after));
+    Ok(after)
+}
+
+/// Moves the item (with everything below it) into `parent` at `index`, both
+/// as in `create`. `index` counts positions after the item left its old place.
+pub fn move_to(id: &str, parent: Option<&str>, index: Option<usize>) -> Result<(), ItemError> {
+    let label = store::update_list(|list| {
+        let item = tree::find(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        if let Some(p) = parent {
+            if tree::subtree_ids(item).iter().any(|i| i == p) {
+                return Err(ItemError::IntoItself(id.to_string()));
+            }
+        }
+        let item = tree::take(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        let label = sync::label(&item).to_string();
+        insert(container(list, parent)?, index, item);
+        Ok(label)
+    })?;
+    sync::record(&format!("Move {}", label));
+    Ok(())
+}
+
+/// Removes the item and everything below it, including their content files.
+/// Returns the removed ids.
+pub fn delete(id: &str) -> Result<Vec<String>, ItemError> {
+    let (removed, label) = store::update_list(|list| {
+        let item = tree::take(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        Ok::<_, ItemError>((tree::subtree_ids(&item), sync::label(&item).to_string()))
+    })?;
+    // after the list is saved: a failed save must not lose content
+    for id in &removed {
+        let _ = fs::remove_file(commands::content_path(id));
+    }
+    sync::record(&format!("Delete {}", label));
+    Ok(removed)
+}
+
+/// Copies the item and everything below it, content included, to right after
+/// the original. Returns the copy.
+pub fn duplicate(id: &str) -> Result<Value, ItemError> {
+    let mut written = Vec::new();
+    let copy = store::update_list(|list| {
+        let (siblings, i) = locate(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        let mut copy = siblings[i].clone();
+        let mut ids = Vec::new();
+        reassign_ids(&mut copy, now_ms(), &mut ids);
+        for (old, new) in &ids {
+            match fs::read(commands::content_path(old)) {
+                Ok(bytes) => {
+                    written.push(new.clone());
+                    store::write_atomic(&commands::content_path(new), &bytes)?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        siblings.insert(i + 1, copy.clone());
+        Ok::<_, ItemError>(copy)
+    });
+    // as in `create`, copies of a rejected duplicate are removed again
+    if copy.is_err() {
+        for id in &written {
+            let _ = fs::remove_file(commands::content_path(id));
+        }
+    }
+    let copy = copy?;
+    sync::record(&format!("Duplicate {}", sync::label(&copy)));
+    Ok(copy)
+}
+
+// The rules below `folder` that a profile switch may turn on or off: pinned
+// rules stay on and system rules are never composed.
+fn switchable(folder: &Value) -> Vec<&Value> {
+    let mut rules = Vec::new();
+    tree::walk(tree::children(folder).map_or(&[], |c| c), &mut |item| {
+        if !tree::is_folder(item) && !flags::is_pinned(item) && !flags::is_system(item) {
+            rules.push(item);
+        }
+    });
+    rules
+}
+
+/// The top-level folders, which double as profiles.
+pub fn profiles(list: &[Value]) -> impl Iterator<Item = &Value> {
+    list.iter().filter(|item| tree::is_folder(item))
+}
+
+/// The profile in effect: the top-level folder whose rules are all on while
+/// those of every other top-level folder are off.
+pub fn active_profile(list: &[Value]) -> Option<&str> {
+    let mut active = profiles(list).filter(|folder| {
+        let rules = switchable(folder);
+        !rules.is_empty() && rules.iter().all(|r| tree::is_on(r))
+    });
+    let folder = active.next()?;
+    let others_off = profiles(list)
+        .filter(|other| tree::id(other) != tree::id(folder))
+        .all(|other| switchable(other).iter().all(|r| !tree::is_on(r)));
+    if others_off {
+        tree::id(folder)
+    } else {
+        None
+    }
+}
+
+/// Switches on every rule below the top-level folder `id` and switches off
+/// the rules below the other top-level folders. Rules outside folders, pinned
+/// and system rules are left alone. Returns whether anything changed.
+pub fn activate_profile(id: &str) -> Result<bool, ItemError> {
+    let (changed, label) = store::update_list(|list| {
+        let folder = list
+            .iter()
+            .find(|item| tree::id(item) == Some(id))
+            .ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+        if !tree::is_folder(folder) {
+            return Err(ItemError::NotAFolder(id.to_string()));
+        }
+        let label = sync::label(folder).to_string();
+        let mut switches = Vec::new();
+        for profile in profiles(list) {
+            let on = tree::id(profile) == Some(id);
+            for rule in switchable(profile) {
+                if tree::is_on(rule) != on {
+                    switches.extend(tree::id(rule).map(|r| (r.to_string(), on)));
+                }
+            }
+        }
+        for (rule, on) in &switches {
+            if let Some(item) = tree::find_mut(list, rule) {
+                item["on"] = json!(on);
+            }
+        }
+        Ok::<_, ItemError>((!switches.is_empty(), label))
+    })?;
+    if changed {
+        sync::record(&format!("Switch to {}", label));
+    }
+    Ok(changed)
+}
@@ -1,6 +1,29 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+pub mod apply;
+pub mod audit;
+pub mod blocklist;
 pub mod commands;
-use tauri::{Builder, Manager};
+pub mod compose;
+pub mod deeplink;
+pub mod export;
+pub mod flags;
+pub mod history;
+pub mod hosts;
+pub mod items;
+pub mod migrations;
+pub mod paths;
+pub mod report;
+pub mod resolver;
+pub mod scheduler;
+pub mod search;
+pub mod settings;
+pub mod share;
+pub mod store;
+pub mod sync;
+pub mod tray;
+pub mod tree;
+pub mod validate;
+use tauri::{Builder, Emitter, Manager, RunEvent, WindowEvent};
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -10,35 +33,144 @@ fn greet(name: &str) -> String {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
-            // Focus main window when a second instance is attempted.
-            if let Some(win) = app.get_webview_window("main") {
-                let _ = win.set_focus();
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            // On Linux and Windows a deep link starts a second instance with
+            // the URL as an argument. Otherwise bring the main window back; it
+            // may be hidden or closed to the tray.
+            let links = deeplink::links_in_args(&argv);
+            if links.is_empty() {
+                let _ = commands::show_main_window(app);
+            }
+            for link in links {
+                deeplink::handle(app, &link);
             }
         }))
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_deep_link::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             commands::ping,
             commands::get_list,
             commands::set_list,
+            commands::get_list_with_revision,
+            commands::get_list_revision,
+            commands::create_item,
+            commands::update_item,
+            commands::move_item,
+            commands::delete_item,
+            commands::duplicate_item,
             commands::get_content_of_list,
+            commands::get_composition,
+            commands::export_as,
+            commands::share_rule,
+            commands::preview_shared_rule,
+            commands::import_shared_rule,
+            commands::get_report,
+            commands::get_variables,
+            commands::set_variables,
+            commands::check_variables,
+            commands::validate_hosts_content,
+            commands::search_rules,
+            commands::refresh_remote,
+            commands::import_blocklist,
+            commands::get_path_of_system_hosts,
             commands::get_system_hosts,
             commands::set_system_hosts,
+            commands::apply_system_hosts,
+            commands::get_history,
+            commands::get_history_content,
+            commands::rollback_history,
+            commands::get_audit_log,
+            commands::get_settings,
+            commands::set_settings,
+            commands::sync_pull,
+            commands::sync_push,
             commands::get_hosts_content,
             commands::set_hosts_content,
             commands::close_main_window,
-            commands::quit
+            commands::quit,
+            commands::take_deep_link
         ])
         .setup(|app| {
+            // Upgrade (or refuse) the on-disk layout before any command touches it.
+            if let Some(from) = paths::migrate_legacy()? {
+                eprintln!("hostsmanager: moved data from {}", from.display());
+            }
+            commands::ensure_data_dir()?;
+            migrations::run(&commands::data_dir())?;
+
+            // Whoever changed the list (UI, tray, scheduler), the tray menu and
+            // the UI follow.
+            tray::init(app.handle())?;
+            let handle = app.handle().clone();
+            store::on_list_changed(move || {
+                tray::refresh(&handle);
+                let _ = handle.emit("LIST_UPDATED", ());
+            });
+
+            // In resolver mode nothing serves the rules until they are applied.
+            if settings::effective().resolver_mode {
+                let res = apply::apply_system_hosts(None, audit::Action::Reapply);
+                if res.get("success").and_then(|v| v.as_bool()) != Some(true) {
+                    eprintln!(
+                        "hostsmanager: cannot start the resolver: {}",
+                        res["message"]
+                    );
+                }
+            }
+
+            // Re-evaluate rule schedules in the background and tell the UI when
+            // they rewrote the system hosts file. Scheduled applies have no
+            // password, so a hosts file the user cannot write makes them fail;
+            // that is reported as a notification, the window may be hidden.
+            let handle = app.handle().clone();
+            app.manage(scheduler::spawn(move |res| {
+                if res.get("success").and_then(|v| v.as_bool()) == Some(true) {
+                    let _ = handle.emit("SYSTEM_HOSTS_UPDATED", res.get("new_content").cloned());
+                } else {
+                    tray::notify_apply_failed(&handle, &res);
+                }
+            }));
+
+            use tauri_plugin_deep_link::DeepLinkExt;
             #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
-            {
-                use tauri_plugin_deep_link::DeepLinkExt;
-                app.deep_link().register_all()?;
+            app.deep_link().register_all()?;
+            // the link this instance was started with, then any later one
+            // (macOS delivers both through `on_open_url`)
+            for url in app.deep_link().get_current()?.unwrap_or_default() {
+                deeplink::handle(app.handle(), url.as_str());
             }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    deeplink::handle(&handle, url.as_str());
+                }
+            });
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running launcher");
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == commands::MAIN_WINDOW
+                    && settings::effective().close_hides_window
+                {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running launcher")
+        .run(|app, event| match event {
+            // Without an exit code this only follows the destruction of the
+            // last window: keep running in the tray. Cmd+Q does not come
+            // through here; it terminates the application and arrives as
+            // `Exit` below.
+            RunEvent::ExitRequested {
+                code: None, api, ..
+            } if app.webview_windows().is_empty() => api.prevent_exit(),
+            // Cmd+Q, the tray's quit, or `quit`
+            RunEvent::Exit => commands::shutdown(app),
+            _ => {}
+        });
 }
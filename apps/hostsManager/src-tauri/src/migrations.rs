@@ -0,0 +1,184 @@
+// On-disk schema versioning for the hostsManager data directory.
+//
+// The layout version is stored in `schema.json` next to `list.json`. A data
+// directory without that file predates versioning and is treated as version 0.
+// Every entry in `MIGRATIONS` upgrades the layout by exactly one version; the
+// version file is rewritten after each step so an interrupted run resumes where
+// it stopped. Before the first step runs, the whole directory is copied to
+// `backups/pre-migration-v{from}-{ms}` so a failed upgrade can be undone by hand.
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Schema version written by this build.
+pub const CURRENT_VERSION: u32 = 1;
+
+const SCHEMA_FILE: &str = "schema.json";
+const BACKUP_DIR: &str = "backups";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error(
+        "data in {dir} uses schema version {found}, but this build only supports up to version {supported}; please upgrade hostsManager"
+    )]
+    NewerVersion {
+        dir: String,
+        found: u32,
+        supported: u32,
+    },
+    #[error("cannot read {file}: {message}")]
+    Corrupt { file: String, message: String },
+    #[error("migration io error on {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+type Step = fn(&Path) -> Result<(), MigrationError>;
+
+// MIGRATIONS[n] upgrades version n to n + 1.
+const MIGRATIONS: [Step; CURRENT_VERSION as usize] = [v0_to_v1];
+
+fn io_err(path: &Path) -> impl FnOnce(std::io::Error) -> MigrationError + '_ {
+    move |source| MigrationError::Io {
+        path: path.to_string_lossy().to_string(),
+        source,
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+/// Returns the schema version of `dir`, or `None` for a fresh (empty) directory.
+pub fn read_version(dir: &Path) -> Result<Option<u32>, MigrationError> {
+    let p = dir.join(SCHEMA_FILE);
+    if !p.exists() {
+        if dir.join("list.json").exists() {
+            return Ok(Some(0));
+        }
+        return Ok(None);
+    }
+    let s = fs::read_to_string(&p).map_err(io_err(&p))?;
+    let v: Value = serde_json::from_str(&s).map_err(|e| MigrationError::Corrupt {
+        file: p.to_string_lossy().to_string(),
+        message: e.to_string(),
+    })?;
+    v.get("version")
+        .and_then(|v| v.as_u64())
+        .map(|v| Some(v as u32))
+        .ok_or_else(|| MigrationError::Corrupt {
+            file: p.to_string_lossy().to_string(),
+            message: "missing numeric `version` field".to_string(),
+        })
+}
+
+fn write_version(dir: &Path, version: u32) -> Result<(), MigrationError> {
+    let p = dir.join(SCHEMA_FILE);
+    fs::write(&p, json!({ "version": version }).to_string()).map_err(io_err(&p))
+}
+
+fn backup(dir: &Path, from: u32) -> Result<PathBuf, MigrationError> {
+    let target = dir
+        .join(BACKUP_DIR)
+        .join(format!("pre-migration-v{}-{}", from, now_ms()));
+    fs::create_dir_all(&target).map_err(io_err(&target))?;
+    copy_dir(dir, &target)?;
+    Ok(target)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), MigrationError> {
+    for entry in fs::read_dir(from).map_err(io_err(from))?.flatten() {
+        let path = entry.path();
+        if entry.file_name() == BACKUP_DIR {
+            continue;
+        }
+        let dest = to.join(entry.file_name());
+        if path.is_dir() {
+            fs::create_dir_all(&dest).map_err(io_err(&dest))?;
+            copy_dir(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest).map_err(io_err(&path))?;
+        }
+    }
+    Ok(())
+}
+
+/// Brings `dir` up to `CURRENT_VERSION`, returning the version found on disk.
+///
+/// Fails without touching anything if the data was written by a newer build.
+pub fn run(dir: &Path) -> Result<u32, MigrationError> {
+    let found = match read_version(dir)? {
+        Some(v) => v,
+        None => {
+            write_version(dir, CURRENT_VERSION)?;
+            return Ok(CURRENT_VERSION);
+        }
+    };
+
+    if found > CURRENT_VERSION {
+        return Err(MigrationError::NewerVersion {
+            dir: dir.to_string_lossy().to_string(),
+            found,
+            supported: CURRENT_VERSION,
+        });
+    }
+    if found == CURRENT_VERSION {
+        return Ok(found);
+    }
+
+    backup(dir, found)?;
+    for version in found..CURRENT_VERSION {
+        MIGRATIONS[version as usize](dir)?;
+        write_version(dir, version + 1)?;
+    }
+    Ok(found)
+}
+
+// v0 -> v1: drop the legacy `groupId`/`color` fields, copy SwitchHosts-style
+// `title` into `name`, and make every node's `type` explicit.
+fn v0_to_v1(dir: &Path) -> Result<(), MigrationError> {
+    let p = dir.join("list.json");
+    if !p.exists() {
+        return Ok(());
+    }
+    let s = fs::read_to_string(&p).map_err(io_err(&p))?;
+    let mut list: Vec<Value> = serde_json::from_str(&s).map_err(|e| MigrationError::Corrupt {
+        file: p.to_string_lossy().to_string(),
+        message: e.to_string(),
+    })?;
+
+    fn upgrade(item: &mut Map<String, Value>) {
+        item.remove("groupId");
+        item.remove("color");
+        if !item.contains_key("name") {
+            if let Some(title) = item.get("title").cloned() {
+                item.insert("name".to_string(), title);
+            }
+        }
+        let is_folder = item.get("children").map(|c| c.is_array()).unwrap_or(false);
+        if !item.contains_key("type") {
+            let t = if is_folder { "folder" } else { "file" };
+            item.insert("type".to_string(), json!(t));
+        }
+        if let Some(children) = item.get_mut("children").and_then(|c| c.as_array_mut()) {
+            for child in children.iter_mut().filter_map(|c| c.as_object_mut()) {
+                upgrade(child);
+            }
+        }
+    }
+
+    for item in list.iter_mut().filter_map(|v| v.as_object_mut()) {
+        upgrade(item);
+    }
+
+    let tmp = dir.join("list.json.tmp");
+    fs::write(&tmp, serde_json::to_string(&list).unwrap_or_default()).map_err(io_err(&tmp))?;
+    fs::rename(&tmp, &p).map_err(io_err(&p))
+}
@@ -0,0 +1,113 @@
+// Platform locations for data, config and cache.
+//
+// Each lives in a `com.tlfsuite.hostsmanager` directory under the platform base
+// directory, which is the same layout Tauri's own path resolver uses:
+//
+//   Linux    $XDG_DATA_HOME, $XDG_CONFIG_HOME, $XDG_CACHE_HOME
+//            (~/.local/share, ~/.config, ~/.cache)
+//   macOS    ~/Library/Application Support (data, config), ~/Library/Caches
+//   Windows  %APPDATA% (data, config), %LOCALAPPDATA% (cache)
+//
+// Older versions kept everything in `~/.hostsmanager`; `migrate_legacy` moves
+// that directory to the new locations once on startup.
+use crate::settings;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const APP_DIR_NAME: &str = "com.tlfsuite.hostsmanager";
+
+fn app_dir(base: Option<PathBuf>) -> PathBuf {
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR_NAME)
+}
+
+/// Data directory used when neither the environment nor the settings choose one.
+pub fn default_data_dir() -> PathBuf {
+    app_dir(dirs_next::data_dir())
+}
+
+/// Where `settings.json` lives by default.
+pub fn config_dir() -> PathBuf {
+    app_dir(dirs_next::config_dir())
+}
+
+/// Scratch space; anything in here may be deleted between runs.
+pub fn cache_dir() -> PathBuf {
+    app_dir(dirs_next::cache_dir())
+}
+
+pub fn legacy_data_dir() -> Option<PathBuf> {
+    dirs_next::home_dir().map(|home| home.join(".hostsmanager"))
+}
+
+fn is_empty_dir(dir: &Path) -> io::Result<bool> {
+    Ok(fs::read_dir(dir)?.next().is_none())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+// rename, falling back to copy + delete across filesystems
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        copy_dir(from, to)?;
+        fs::remove_dir_all(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Moves `~/.hostsmanager` to the platform locations: `settings.json` to the
+/// config directory, everything else to the data directory. Nothing is touched
+/// when the data directory is chosen explicitly (settings or environment) or
+/// when the new location already holds data. Returns the directory migrated
+/// from, if any.
+pub fn migrate_legacy() -> io::Result<Option<PathBuf>> {
+    let Some(legacy) = legacy_data_dir() else {
+        return Ok(None);
+    };
+    if !legacy.is_dir() {
+        return Ok(None);
+    }
+
+    let legacy_settings = legacy.join("settings.json");
+    let new_settings = settings::settings_path();
+    if legacy_settings.is_file() && !new_settings.exists() {
+        move_path(&legacy_settings, &new_settings)?;
+    }
+
+    if settings::effective().data_dir.is_some() {
+        return Ok(None);
+    }
+    let target = default_data_dir();
+    if target == legacy {
+        return Ok(None);
+    }
+    if target.exists() {
+        if !is_empty_dir(&target)? {
+            return Ok(None);
+        }
+        fs::remove_dir(&target)?;
+    }
+    move_path(&legacy, &target)?;
+    Ok(Some(legacy))
+}
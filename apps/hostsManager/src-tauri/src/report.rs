@@ -0,0 +1,218 @@
+// Per-rule statistics, for cleaning up setups that have grown over the years.
+//
+// Figures come from each rule's content as stored: includes and variables are
+// not expanded. Wildcard and regex hosts count as hostnames but not towards
+// conflicts. A conflict is a hostname a rule maps to an address of one family
+// while another enabled rule maps it to a different address of the same
+// family; which one wins is up to priorities (see `compose`).
+//
+// "Last applied" is the last time a successful write of the system hosts file,
+// or an apply in resolver mode, carried the rule's block. Those times are kept
+// in `applied.json` in the data directory as rule id -> time in ms, so a rule
+// last applied before that file existed has none.
+use crate::hosts::{self, Line};
+use crate::{commands, flags, store, tree};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RULE_HEADER: &str = "# --- rule: ";
+
+/// Another enabled rule mapping `hostname` elsewhere.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleConflict {
+    pub hostname: String,
+    pub ip: String,
+    pub rule_id: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RuleReport {
+    pub id: String,
+    pub title: String,
+    /// Composed on the next apply (enabled or pinned, and not `system`).
+    pub enabled: bool,
+    pub entries: usize,
+    pub hostnames: usize,
+    /// Distinct addresses, sorted.
+    pub ips: Vec<String>,
+    /// Line numbers of lines that are not hosts entries.
+    pub invalid_lines: Vec<usize>,
+    pub conflicts: Vec<RuleConflict>,
+    /// Modification time of the content file.
+    pub modified_ms: Option<u64>,
+    pub applied_ms: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Report {
+    /// Every rule in tree order; folders are left out.
+    pub rules: Vec<RuleReport>,
+    /// Size in bytes of what an apply would write below the marker; `None`
+    /// when composition fails, with the reason in `compose_error`.
+    pub composed_bytes: Option<usize>,
+    pub composed_lines: Option<usize>,
+    pub compose_error: Option<String>,
+}
+
+fn applied_path() -> PathBuf {
+    commands::data_dir().join("applied.json")
+}
+
+fn read_applied() -> HashMap<String, u64> {
+    fs::read(applied_path())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Notes that the rule blocks in `content` were just applied.
+pub fn record_applied(content: &str) {
+    let ids: Vec<&str> = content
+        .lines()
+        .filter_map(|l| {
+            l.trim_end()
+                .strip_prefix(RULE_HEADER)?
+                .strip_suffix(") ---")
+        })
+        .filter_map(|l| l.rsplit_once(" (").map(|(_, id)| id))
+        .collect();
+    if ids.is_empty() {
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut applied = read_applied();
+    for id in ids {
+        applied.insert(id.to_string(), now);
+    }
+    let result = commands::ensure_data_dir().and_then(|_| {
+        let bytes = serde_json::to_vec(&applied).map_err(std::io::Error::other)?;
+        store::write_atomic(&applied_path(), &bytes)
+    });
+    if let Err(e) = result {
+        eprintln!("hostsmanager: cannot record applied rules: {}", e);
+    }
+}
+
+fn modified_ms(id: &str) -> Option<u64> {
+    let modified = fs::metadata(commands::content_path(id))
+        .ok()?
+        .modified()
+        .ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+// The (hostname, address) pairs of a rule, hostnames lowercased.
+struct Parsed {
+    entries: usize,
+    pairs: Vec<(String, IpAddr)>,
+    invalid_lines: Vec<usize>,
+}
+
+fn parse(content: &str) -> Parsed {
+    let mut parsed = Parsed {
+        entries: 0,
+        pairs: Vec::new(),
+        invalid_lines: Vec::new(),
+    };
+    for (n, raw) in hosts::lines(content) {
+        match hosts::parse_line(raw) {
+            Line::Entry { ip, hosts, .. } => {
+                parsed.entries += 1;
+                for host in hosts {
+                    let host = host.trim_end_matches('.').to_ascii_lowercase();
+                    parsed.pairs.push((host, ip));
+                }
+            }
+            Line::Invalid(_) => parsed.invalid_lines.push(n),
+            Line::Blank | Line::Comment(_) => {}
+        }
+    }
+    parsed
+}
+
+/// Builds the report for `list`, reading each rule's content with `load`
+/// and taking the composed output from `composed`.
+pub fn build(
+    list: &[Value],
+    load: impl Fn(&Value) -> Option<String>,
+    composed: Result<String, String>,
+) -> Report {
+    let mut rules = Vec::new();
+    tree::walk(list, &mut |item| {
+        if !tree::is_folder(item) {
+            if let Some(id) = tree::id(item) {
+                rules.push((id, item, parse(&load(item).unwrap_or_default())));
+            }
+        }
+    });
+
+    // hostname -> (rule index, address) over the enabled rules
+    let mut mapped: HashMap<&str, Vec<(usize, IpAddr)>> = HashMap::new();
+    for (i, (_, item, parsed)) in rules.iter().enumerate() {
+        if !flags::is_active(item) {
+            continue;
+        }
+        for (host, ip) in &parsed.pairs {
+            if !hosts::is_pattern(host) {
+                mapped.entry(host.as_str()).or_default().push((i, *ip));
+            }
+        }
+    }
+
+    let applied = read_applied();
+    let reports = rules
+        .iter()
+        .enumerate()
+        .map(|(i, (id, item, parsed))| {
+            let mut conflicts = Vec::new();
+            for (host, ip) in &parsed.pairs {
+                for &(j, other) in mapped.get(host.as_str()).into_iter().flatten() {
+                    let clash = j != i && other != *ip && other.is_ipv4() == ip.is_ipv4();
+                    let conflict = RuleConflict {
+                        hostname: host.clone(),
+                        ip: other.to_string(),
+                        rule_id: rules[j].0.to_string(),
+                        title: tree::title(rules[j].1).to_string(),
+                    };
+                    if clash && !conflicts.contains(&conflict) {
+                        conflicts.push(conflict);
+                    }
+                }
+            }
+            let hostnames: BTreeSet<&str> = parsed.pairs.iter().map(|(h, _)| h.as_str()).collect();
+            let ips: BTreeSet<IpAddr> = parsed.pairs.iter().map(|(_, ip)| *ip).collect();
+            RuleReport {
+                id: id.to_string(),
+                title: tree::title(item).to_string(),
+                enabled: flags::is_active(item),
+                entries: parsed.entries,
+                hostnames: hostnames.len(),
+                ips: ips.iter().map(IpAddr::to_string).collect(),
+                invalid_lines: parsed.invalid_lines.clone(),
+                conflicts,
+                modified_ms: modified_ms(id),
+                applied_ms: applied.get(*id).copied(),
+            }
+        })
+        .collect();
+
+    let (composed_bytes, composed_lines, compose_error) = match composed {
+        Ok(content) => (Some(content.len()), Some(content.lines().count()), None),
+        Err(e) => (None, None, Some(e)),
+    };
+    Report {
+        rules: reports,
+        composed_bytes,
+        composed_lines,
+        compose_error,
+    }
+}
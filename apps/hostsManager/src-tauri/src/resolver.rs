@@ -0,0 +1,385 @@
+// Embedded DNS resolver, an alternative to writing the system hosts file.
+//
+// With `resolver_mode` on, applying rules loads the composed mappings into a
+// small UDP DNS server on `127.0.0.1:<resolver_port>` instead of writing
+// `/etc/hosts`, so no privilege escalation is needed. The system (or a local
+// dnsmasq / systemd-resolved) has to be pointed at that port.
+//
+// Names mapped by the rules are answered here for every query type: A and
+// AAAA get the addresses of the matching family, anything else an empty
+// answer, so a blocked name cannot leak through other record types. Wildcard
+// and regex hosts (see `hosts::Pattern`) are served as they are: `*.dev.local`
+// covers every name below `dev.local` (not `dev.local` itself). Exact names win
+// over patterns, longer wildcards over shorter ones and wildcards over regexes;
+// invalid patterns are ignored. All other queries are forwarded unchanged to
+// `resolver_upstream` by a few worker threads; when it does not answer, or
+// more queries are waiting for it than `FORWARD_QUEUE`, the client gets
+// SERVFAIL.
+use crate::hosts::{self, Line, Pattern};
+use crate::settings::Settings;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// TTL of local answers; short, so toggled rules take effect quickly.
+pub const TTL: u32 = 10;
+pub const DEFAULT_UPSTREAM_PORT: u16 = 53;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_MESSAGE: usize = 4096;
+/// Threads forwarding queries to the upstream.
+pub const FORWARD_WORKERS: usize = 4;
+/// Queries that may wait for a forwarding thread before SERVFAIL is returned.
+pub const FORWARD_QUEUE: usize = 64;
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const RCODE_SERVFAIL: u8 = 2;
+
+/// Hostname -> addresses, built from hosts content.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    exact: HashMap<String, Vec<IpAddr>>,
+    // most specific first
+    patterns: Vec<(Pattern, Vec<IpAddr>)>,
+}
+
+fn push_unique(addrs: &mut Vec<IpAddr>, ip: IpAddr) {
+    if !addrs.contains(&ip) {
+        addrs.push(ip);
+    }
+}
+
+impl Table {
+    pub fn from_hosts(content: &str) -> Table {
+        let mut table = Table::default();
+        let mut patterns: HashMap<String, usize> = HashMap::new();
+        for (_, raw) in hosts::lines(content) {
+            let Line::Entry { ip, hosts, .. } = hosts::parse_line(raw) else {
+                continue;
+            };
+            for host in hosts {
+                if !hosts::is_pattern(host) {
+                    let host = host.trim_end_matches('.').to_ascii_lowercase();
+                    push_unique(table.exact.entry(host).or_default(), ip);
+                    continue;
+                }
+                let i = match patterns.get(host) {
+                    Some(&i) => i,
+                    None => {
+                        let Ok(pattern) = Pattern::new(host) else {
+                            continue;
+                        };
+                        table.patterns.push((pattern, Vec::new()));
+                        patterns.insert(host.to_string(), table.patterns.len() - 1);
+                        table.patterns.len() - 1
+                    }
+                };
+                push_unique(&mut table.patterns[i].1, ip);
+            }
+        }
+        // stable: equally specific patterns keep their order
+        table
+            .patterns
+            .sort_by_key(|(pattern, _)| Reverse(pattern.specificity()));
+        table
+    }
+
+    /// The addresses for `name` (lowercase, no trailing dot), if the rules map it.
+    pub fn lookup(&self, name: &str) -> Option<&[IpAddr]> {
+        if let Some(addrs) = self.exact.get(name) {
+            return Some(addrs);
+        }
+        self.patterns
+            .iter()
+            .find(|(pattern, _)| pattern.matches(name))
+            .map(|(_, addrs)| addrs.as_slice())
+    }
+}
+
+/// Parses an upstream given as `ip` or `ip:port`; the port defaults to 53.
+pub fn parse_upstream(s: &str) -> Option<SocketAddr> {
+    let s = s.trim();
+    s.parse::<SocketAddr>().ok().or_else(|| {
+        s.parse::<IpAddr>()
+            .ok()
+            .map(|ip| (ip, DEFAULT_UPSTREAM_PORT).into())
+    })
+}
+
+struct Question {
+    name: String,
+    qtype: u16,
+    qclass: u16,
+    // offset just past the question section
+    end: usize,
+}
+
+fn u16_at(msg: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*msg.get(pos)?, *msg.get(pos + 1)?]))
+}
+
+// The single question of a standard query.
+fn parse_question(msg: &[u8]) -> Option<Question> {
+    let flags = u16_at(msg, 2)?;
+    let opcode = (flags >> 11) & 0xF;
+    if flags & 0x8000 != 0 || opcode != 0 || u16_at(msg, 4)? != 1 {
+        return None;
+    }
+    let mut pos = 12;
+    let mut labels = Vec::new();
+    loop {
+        let len = *msg.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // compression pointers have no business in a question
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        let label = msg.get(pos..pos + len)?;
+        labels.push(std::str::from_utf8(label).ok()?.to_ascii_lowercase());
+        pos += len;
+    }
+    Some(Question {
+        name: labels.join("."),
+        qtype: u16_at(msg, pos)?,
+        qclass: u16_at(msg, pos + 2)?,
+        end: pos + 4,
+    })
+}
+
+// Header of a reply to `query` with the question copied over.
+fn reply_header(query: &[u8], q_end: usize, rcode: u8, answers: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(q_end + answers as usize * 28);
+    out.extend_from_slice(&query[..2]);
+    // QR, AA and the client's RD; RA
+    out.push(0x84 | (query[2] & 0x01));
+    out.push(0x80 | rcode);
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&answers.to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&query[12..q_end]);
+    out
+}
+
+/// The reply to `query` when the rules decide it, `None` when it should be
+/// forwarded.
+pub fn answer(query: &[u8], table: &Table) -> Option<Vec<u8>> {
+    let q = parse_question(query)?;
+    if q.qclass != CLASS_IN {
+        return None;
+    }
+    let addrs = table.lookup(&q.name)?;
+    let records: Vec<&IpAddr> = addrs
+        .iter()
+        .filter(|ip| match q.qtype {
+            TYPE_A => ip.is_ipv4(),
+            TYPE_AAAA => ip.is_ipv6(),
+            _ => false,
+        })
+        .collect();
+    let mut out = reply_header(query, q.end, 0, records.len() as u16);
+    for ip in records {
+        let (qtype, data) = match ip {
+            IpAddr::V4(a) => (TYPE_A, a.octets().to_vec()),
+            IpAddr::V6(a) => (TYPE_AAAA, a.octets().to_vec()),
+        };
+        // the name is a pointer to the question
+        out.extend_from_slice(&[0xC0, 0x0C]);
+        out.extend_from_slice(&qtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&TTL.to_be_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        out.extend_from_slice(&data);
+    }
+    Some(out)
+}
+
+fn servfail(query: &[u8]) -> Vec<u8> {
+    match parse_question(query) {
+        Some(q) => reply_header(query, q.end, RCODE_SERVFAIL, 0),
+        None => {
+            let mut out = reply_header(query, 12, RCODE_SERVFAIL, 0);
+            out[4..6].copy_from_slice(&0u16.to_be_bytes());
+            out
+        }
+    }
+}
+
+fn forward(query: &[u8], upstream: SocketAddr) -> Option<Vec<u8>> {
+    let local: SocketAddr = if upstream.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local).ok()?;
+    socket.set_read_timeout(Some(FORWARD_TIMEOUT)).ok()?;
+    socket.send_to(query, upstream).ok()?;
+    let mut buf = [0u8; MAX_MESSAGE];
+    loop {
+        let (n, from) = socket.recv_from(&mut buf).ok()?;
+        if from == upstream && n >= 12 && buf[..2] == query[..2] {
+            return Some(buf[..n].to_vec());
+        }
+    }
+}
+
+/// A running resolver; stopped when dropped.
+pub struct Server {
+    addr: SocketAddr,
+    upstream: SocketAddr,
+    table: Arc<RwLock<Table>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Binds `bind` (port 0 picks a free one) and starts serving `table`.
+    pub fn start(bind: SocketAddr, upstream: SocketAddr, table: Table) -> io::Result<Server> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let addr = socket.local_addr()?;
+        let table = Arc::new(RwLock::new(table));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let table = table.clone();
+            let stop = stop.clone();
+            thread::spawn(move || serve(socket, upstream, &table, &stop))
+        };
+        Ok(Server {
+            addr,
+            upstream,
+            table,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn set_table(&self, table: Table) {
+        *self.table.write().unwrap_or_else(|e| e.into_inner()) = table;
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+type Forward = (Vec<u8>, SocketAddr);
+
+// Forwards queued queries until the queue is closed. Once the server stops,
+// what is still queued gets SERVFAIL rather than a wait for the upstream.
+fn forward_queued(
+    socket: &UdpSocket,
+    upstream: SocketAddr,
+    queue: &Mutex<Receiver<Forward>>,
+    stop: &AtomicBool,
+) {
+    loop {
+        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let Ok((query, peer)) = next else {
+            return;
+        };
+        let reply = if stop.load(Ordering::SeqCst) {
+            None
+        } else {
+            forward(&query, upstream)
+        };
+        let reply = reply.unwrap_or_else(|| servfail(&query));
+        let _ = socket.send_to(&reply, peer);
+    }
+}
+
+fn serve(socket: UdpSocket, upstream: SocketAddr, table: &RwLock<Table>, stop: &Arc<AtomicBool>) {
+    // the upstream may be slow; local names keep being answered meanwhile
+    let (forwards, queue) = mpsc::sync_channel::<Forward>(FORWARD_QUEUE);
+    let queue = Arc::new(Mutex::new(queue));
+    let workers: Vec<JoinHandle<()>> = (0..FORWARD_WORKERS)
+        .filter_map(|_| {
+            let socket = socket.try_clone().ok()?;
+            let (queue, stop) = (queue.clone(), stop.clone());
+            Some(thread::spawn(move || {
+                forward_queued(&socket, upstream, &queue, &stop)
+            }))
+        })
+        .collect();
+
+    let mut buf = [0u8; MAX_MESSAGE];
+    while !stop.load(Ordering::SeqCst) {
+        let Ok((n, peer)) = socket.recv_from(&mut buf) else {
+            continue;
+        };
+        let query = &buf[..n];
+        // too short for a header, or a response
+        if n < 12 || query[2] & 0x80 != 0 {
+            continue;
+        }
+        let reply = answer(query, &table.read().unwrap_or_else(|e| e.into_inner()));
+        match reply {
+            Some(reply) => {
+                let _ = socket.send_to(&reply, peer);
+            }
+            None => match forwards.try_send((query.to_vec(), peer)) {
+                Ok(()) => {}
+                Err(TrySendError::Full((query, _)) | TrySendError::Disconnected((query, _))) => {
+                    let _ = socket.send_to(&servfail(&query), peer);
+                }
+            },
+        }
+    }
+    // the workers hold the port too; it is free once they are done
+    drop(forwards);
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+static RUNNING: Mutex<Option<Server>> = Mutex::new(None);
+
+/// Serves `content` on the port from `settings`, starting the resolver or
+/// restarting it when the port or upstream changed. Returns its address.
+pub fn apply(content: &str, settings: &Settings) -> io::Result<SocketAddr> {
+    let bind: SocketAddr = (Ipv4Addr::LOCALHOST, settings.resolver_port).into();
+    let upstream = parse_upstream(&settings.resolver_upstream).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid resolver_upstream: {}", settings.resolver_upstream),
+        )
+    })?;
+    let table = Table::from_hosts(content);
+    let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(server) = running.as_ref() {
+        if server.addr == bind && server.upstream == upstream {
+            server.set_table(table);
+            return Ok(server.addr);
+        }
+    }
+    // release the old port before binding, it may be the same one
+    running.take();
+    let server = Server::start(bind, upstream, table)?;
+    let addr = server.addr;
+    *running = Some(server);
+    Ok(addr)
+}
+
+/// Stops the resolver if it is running.
+pub fn stop() {
+    RUNNING.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
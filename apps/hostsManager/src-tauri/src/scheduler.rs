@@ -0,0 +1,204 @@
+// Time-based activation of rules.
+//
+// An item may carry a `schedule`:
+//
+//   "schedule": {
+//     "windows": [{ "start": "09:00", "end": "18:00" }],  // local time, may wrap midnight
+//     "weekdays": [1, 2, 3, 4, 5],                         // 1 = Monday .. 7 = Sunday
+//     "expires_at_ms": 1700000000000                       // one-off: switch off after this
+//   }
+//
+// With `windows` and/or `weekdays` the item should be on while today is a
+// listed weekday (any day if empty) and the time falls in a window (all day if
+// empty). The item is switched when that changes, at a window or day
+// boundary, and the state it was switched to is kept as `scheduled_on`; in
+// between, a manual toggle holds until the next boundary. `expires_at_ms`
+// turns the item off once and is then removed, which is how "enable for 2
+// hours" is expressed. A background thread re-evaluates every schedule
+// periodically and re-applies the system hosts file when a rule was switched.
+// Pinned items are never switched off.
+use crate::store::StoreError;
+use crate::{apply, audit, commands};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+/// How long `Scheduler::stop` waits for a pass in progress.
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug, Default)]
+struct Schedule {
+    #[serde(default)]
+    windows: Vec<Window>,
+    #[serde(default)]
+    weekdays: Vec<u32>,
+    expires_at_ms: Option<i64>,
+    scheduled_on: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Window {
+    start: String,
+    end: String,
+}
+
+// "HH:MM" -> minutes since midnight
+fn parse_hhmm(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+impl Window {
+    fn contains(&self, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_hhmm(&self.start), parse_hhmm(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= minute && minute < end
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+impl Schedule {
+    // Desired `on` state, or `None` when the schedule does not constrain it.
+    fn desired(&self, now: &DateTime<Local>) -> Option<bool> {
+        if self.expired(now) {
+            return Some(false);
+        }
+        if self.windows.is_empty() && self.weekdays.is_empty() {
+            return None;
+        }
+        let weekday = now.weekday().number_from_monday();
+        let minute = now.hour() * 60 + now.minute();
+        let day_ok = self.weekdays.is_empty() || self.weekdays.contains(&weekday);
+        let time_ok = self.windows.is_empty() || self.windows.iter().any(|w| w.contains(minute));
+        Some(day_ok && time_ok)
+    }
+
+    fn expired(&self, now: &DateTime<Local>) -> bool {
+        self.expires_at_ms
+            .map(|t| now.timestamp_millis() >= t)
+            .unwrap_or(false)
+    }
+}
+
+/// Applies every schedule in `list` at `now`. Returns true if a rule was
+/// switched; the schedules' own bookkeeping may change `list` either way.
+pub fn evaluate(list: &mut [Value], now: &DateTime<Local>) -> bool {
+    let mut changed = false;
+    for item in list.iter_mut() {
+        if let Some(obj) = item.as_object_mut() {
+            let schedule = obj
+                .get("schedule")
+                .and_then(|s| serde_json::from_value::<Schedule>(s.clone()).ok());
+            if let Some(schedule) = schedule {
+                let on = obj.get("on").and_then(|v| v.as_bool()).unwrap_or(false);
+                let pinned = obj.get("pinned").and_then(|v| v.as_bool()).unwrap_or(false);
+                let want = schedule.desired(now);
+                // expiry always switches off; windows only when they move on
+                let expired = schedule.expired(now);
+                if let Some(want) =
+                    want.filter(|&want| expired || schedule.scheduled_on != Some(want))
+                {
+                    if want != on && (want || !pinned) {
+                        obj.insert("on".to_string(), Value::Bool(want));
+                        changed = true;
+                    }
+                }
+                if let Some(s) = obj.get_mut("schedule").and_then(|s| s.as_object_mut()) {
+                    if let Some(want) = want {
+                        s.insert("scheduled_on".to_string(), Value::Bool(want));
+                    }
+                    if expired {
+                        s.remove("expires_at_ms");
+                    }
+                }
+            }
+        }
+        if let Some(children) = item.get_mut("children").and_then(|c| c.as_array_mut()) {
+            changed |= evaluate(children, now);
+        }
+    }
+    changed
+}
+
+/// Runs one scheduling pass. When the list changed it is saved, and when a
+/// rule was switched the system hosts file is re-applied; the result of
+/// `set_system_hosts` is returned.
+pub fn tick(now: &DateTime<Local>) -> Option<Value> {
+    let commands::ListSnapshot { mut list, revision } = commands::get_list_with_revision();
+    let before = list.clone();
+    let switched = evaluate(&mut list, now);
+    if list == before {
+        return None;
+    }
+    match commands::set_list(list, Some(revision)) {
+        Ok(_) => {}
+        // edited meanwhile; the next pass evaluates the new list
+        Err(StoreError::Conflict { .. }) => return None,
+        Err(e) => {
+            return Some(serde_json::json!({
+                "success": false,
+                "code": "write_failed",
+                "message": e.to_string()
+            }))
+        }
+    }
+    if !switched {
+        return None;
+    }
+    Some(apply::apply_system_hosts(None, audit::Action::Reapply))
+}
+
+/// Handle to the background scheduler thread.
+pub struct Scheduler {
+    stop: Mutex<Option<Sender<()>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    /// Stops the thread and waits up to `STOP_TIMEOUT` for an in-flight pass
+    /// to finish. Quitting runs on the main thread, and a pass may be waiting
+    /// for the main thread itself, so an unbounded join could hang the app.
+    pub fn stop(&self) {
+        if let Ok(mut stop) = self.stop.lock() {
+            stop.take();
+        }
+        if let Some(t) = self.thread.lock().ok().and_then(|mut t| t.take()) {
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while !t.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(20));
+            }
+            if t.is_finished() {
+                let _ = t.join();
+            }
+        }
+    }
+}
+
+/// Starts the scheduler. `on_change` receives the apply result of every pass
+/// that changed the list.
+pub fn spawn(on_change: impl Fn(Value) + Send + 'static) -> Scheduler {
+    let (tx, rx) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || loop {
+        if let Some(res) = tick(&Local::now()) {
+            on_change(res);
+        }
+        match rx.recv_timeout(TICK_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            _ => break,
+        }
+    });
+    Scheduler {
+        stop: Mutex::new(Some(tx)),
+        thread: Mutex::new(Some(thread)),
+    }
+}
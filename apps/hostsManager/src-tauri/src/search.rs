@@ -0,0 +1,134 @@
+// Cross-rule search for `search_rules`.
+//
+// A query is interpreted, in order, as:
+//   /pattern/      regex matched against rule titles and raw entry lines
+//   10.0.0.0/8     CIDR range matched against entry IPs
+//   10.0.0.1, ::1  exact IP matched against entry IPs
+//   anything else  case-insensitive hostname substring (also matches titles)
+use crate::hosts::{self, Line};
+use crate::tree;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use serde_json::Value;
+use std::net::IpAddr;
+
+pub enum Query {
+    Regex(Regex),
+    Cidr(IpAddr, u8),
+    Ip(IpAddr),
+    Host(String),
+}
+
+impl Query {
+    pub fn parse(raw: &str) -> Result<Query, String> {
+        let q = raw.trim();
+        if q.is_empty() {
+            return Err("empty search query".to_string());
+        }
+
+        if q.len() > 2 && q.starts_with('/') && q.ends_with('/') {
+            let pattern = &q[1..q.len() - 1];
+            return RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(Query::Regex)
+                .map_err(|e| format!("invalid regex: {}", e));
+        }
+
+        if let Some((addr, prefix)) = q.split_once('/') {
+            if let (Some(ip), Ok(prefix)) = (hosts::parse_ip(addr), prefix.parse::<u8>()) {
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                if prefix > max {
+                    return Err(format!("invalid CIDR prefix /{}", prefix));
+                }
+                return Ok(Query::Cidr(ip, prefix));
+            }
+        }
+
+        if let Some(ip) = hosts::parse_ip(q) {
+            return Ok(Query::Ip(ip));
+        }
+
+        Ok(Query::Host(q.to_ascii_lowercase()))
+    }
+
+    fn matches_title(&self, title: &str) -> bool {
+        match self {
+            Query::Regex(re) => re.is_match(title),
+            Query::Host(h) => title.to_ascii_lowercase().contains(h.as_str()),
+            Query::Cidr(..) | Query::Ip(_) => false,
+        }
+    }
+
+    fn matches_line(&self, raw: &str, line: &Line) -> bool {
+        match (self, line) {
+            (Query::Regex(re), Line::Entry { .. } | Line::Invalid(_)) => re.is_match(raw.trim()),
+            (Query::Ip(q), Line::Entry { ip, .. }) => ip == q,
+            (Query::Cidr(net, prefix), Line::Entry { ip, .. }) => in_cidr(ip, net, *prefix),
+            (Query::Host(q), Line::Entry { hosts, .. }) => hosts
+                .iter()
+                .any(|h| h.to_ascii_lowercase().contains(q.as_str())),
+            _ => false,
+        }
+    }
+}
+
+fn in_cidr(ip: &IpAddr, net: &IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(*a) & mask == u32::from(*b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(*a) & mask == u128::from(*b) & mask
+        }
+        _ => false,
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchHit {
+    pub rule_id: String,
+    pub title: String,
+    /// 1-based line in the rule content; `None` when the title matched.
+    pub line: Option<usize>,
+    pub enabled: bool,
+    pub entry: String,
+}
+
+/// Searches every rule title and content. `load` returns the content of a rule
+/// by id, or `None` when it has no content file.
+pub fn search(
+    list: &[Value],
+    query: &Query,
+    load: impl Fn(&str) -> Option<String>,
+) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    tree::walk(list, &mut |item| {
+        let Some(id) = tree::id(item) else {
+            return;
+        };
+        let title = tree::title(item);
+        let enabled = tree::is_on(item);
+        let hit = |line: Option<usize>, entry: &str| SearchHit {
+            rule_id: id.to_string(),
+            title: title.to_string(),
+            line,
+            enabled,
+            entry: entry.to_string(),
+        };
+
+        if query.matches_title(title) {
+            hits.push(hit(None, title));
+        }
+        if let Some(content) = load(id) {
+            for (n, raw) in hosts::lines(&content) {
+                if query.matches_line(raw, &hosts::parse_line(raw)) {
+                    hits.push(hit(Some(n), raw.trim()));
+                }
+            }
+        }
+    });
+    hits
+}
@@ -0,0 +1,251 @@
+// Persisted, typed settings for hostsManager.
+//
+// Values are layered: built-in defaults, then `settings.json`, then environment
+// variables (`HOSTSMANAGER_DATA_DIR`, `HOSTSMANAGER_SAFE_MODE`,
+// `SWEETHOSTS_SAFE_MODE`, `HOSTSMANAGER_SAFE_HOSTS_PATH`), which always win so
+// scripted and test runs keep working. `set_settings` only ever persists the
+// file layer. Settings are read on nearly every command (the data directory is
+// one of them), so the parsed file is kept until its modification time changes
+// or `save` replaces it.
+//
+// `settings.json` cannot live in the configurable data directory, so it is
+// looked up in `HOSTSMANAGER_CONFIG_DIR`, else in `HOSTSMANAGER_DATA_DIR` (so an
+// env-pointed sandbox is self-contained), else in the platform config directory.
+use crate::{hosts, paths, resolver};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    /// Keep the existing system hosts content and manage only the block below
+    /// the `HOSTSMANAGER_CONTENT_START` marker.
+    #[default]
+    ManagedBlock,
+    /// Replace the whole system hosts file with the composed rules.
+    WholeFile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    /// CRLF on Windows, LF elsewhere.
+    #[default]
+    Auto,
+    Lf,
+    Crlf,
+}
+
+impl LineEndings {
+    /// Rewrites every line break in `content` to this style.
+    pub fn apply(self, content: &str) -> String {
+        let crlf = match self {
+            LineEndings::Auto => cfg!(target_os = "windows"),
+            LineEndings::Lf => false,
+            LineEndings::Crlf => true,
+        };
+        let lf = content.replace("\r\n", "\n");
+        if crlf {
+            lf.replace('\n', "\r\n")
+        } else {
+            lf
+        }
+    }
+}
+
+pub const MAX_HISTORY_RETENTION: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Settings {
+    /// Where rules are stored; `None` uses the platform default.
+    pub data_dir: Option<String>,
+    /// Read and write a sandbox file instead of the real system hosts file.
+    pub safe_mode: bool,
+    /// The sandbox file used in safe mode; `None` uses `sandbox/hosts` in the
+    /// data directory.
+    pub safe_hosts_path: Option<String>,
+    pub write_mode: WriteMode,
+    /// Flush the OS DNS cache after a successful write.
+    pub flush_dns: bool,
+    /// How many previous versions of the system hosts file to keep; 0 disables history.
+    pub history_retention: usize,
+    pub line_endings: LineEndings,
+    /// Closing the main window hides it instead of destroying it; either way
+    /// the app keeps running in the tray.
+    pub close_hides_window: bool,
+    /// Apply serves the rules from the embedded DNS resolver on
+    /// `127.0.0.1:resolver_port` instead of writing the system hosts file,
+    /// which is left as it is; entries still in it take precedence.
+    pub resolver_mode: bool,
+    pub resolver_port: u16,
+    /// Where queries for names without a rule go, as `ip` or `ip:port`.
+    pub resolver_upstream: String,
+    /// Concrete hostnames that wildcard and regex hosts expand to when the
+    /// rules are composed for the hosts file.
+    pub pattern_hosts: Vec<String>,
+    /// Give loopback and blocking IPv4 entries a matching IPv6 entry when the
+    /// rules are applied (see `ComposeOptions::ipv6_companions`).
+    pub ipv6_companions: bool,
+    /// Commit every change of the rules to a git repository in the data
+    /// directory, to be pulled from and pushed to `sync_remote`.
+    pub sync_enabled: bool,
+    /// Any URL or path git can pull from and push to.
+    pub sync_remote: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            data_dir: None,
+            safe_mode: false,
+            safe_hosts_path: None,
+            write_mode: WriteMode::default(),
+            flush_dns: false,
+            history_retention: 20,
+            line_endings: LineEndings::default(),
+            close_hides_window: false,
+            resolver_mode: false,
+            resolver_port: 5354,
+            resolver_upstream: "1.1.1.1:53".to_string(),
+            pattern_hosts: Vec::new(),
+            ipv6_companions: false,
+            sync_enabled: false,
+            sync_remote: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(dir) = &self.data_dir {
+            if dir.trim().is_empty() {
+                return Err("data_dir must not be empty".to_string());
+            }
+            if !Path::new(dir).is_absolute() {
+                return Err(format!("data_dir must be an absolute path: {}", dir));
+            }
+        }
+        if let Some(p) = &self.safe_hosts_path {
+            if !Path::new(p).is_absolute() {
+                return Err(format!("safe_hosts_path must be an absolute path: {}", p));
+            }
+        }
+        if let Some(r) = &self.sync_remote {
+            if r.trim().is_empty() {
+                return Err("sync_remote must not be empty".to_string());
+            }
+        }
+        if self.resolver_port == 0 {
+            return Err("resolver_port must not be 0".to_string());
+        }
+        if resolver::parse_upstream(&self.resolver_upstream).is_none() {
+            return Err(format!(
+                "resolver_upstream must be an ip or ip:port: {}",
+                self.resolver_upstream
+            ));
+        }
+        if let Some(h) = self
+            .pattern_hosts
+            .iter()
+            .find(|h| h.is_empty() || h.contains(char::is_whitespace) || hosts::is_pattern(h))
+        {
+            return Err(format!("pattern_hosts must be plain hostnames: {:?}", h));
+        }
+        if self.history_retention > MAX_HISTORY_RETENTION {
+            return Err(format!(
+                "history_retention must be at most {}",
+                MAX_HISTORY_RETENTION
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Settings as seen by the UI: the effective values plus which of them are
+/// pinned by environment variables.
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingsView {
+    pub effective: Settings,
+    pub stored: Settings,
+    pub env_overrides: Vec<&'static str>,
+}
+
+pub fn settings_path() -> PathBuf {
+    let dir = env::var("HOSTSMANAGER_CONFIG_DIR")
+        .or_else(|_| env::var("HOSTSMANAGER_DATA_DIR"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| paths::config_dir());
+    dir.join("settings.json")
+}
+
+// The file layer as last read: its path, its modification time and the values.
+type Cached = (PathBuf, Option<SystemTime>, Settings);
+static STORED: Mutex<Option<Cached>> = Mutex::new(None);
+
+/// The file layer; missing or unreadable files yield defaults.
+pub fn stored() -> Settings {
+    let path = settings_path();
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    let mut cache = STORED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((p, m, settings)) = cache.as_ref() {
+        if *p == path && *m == modified {
+            return settings.clone();
+        }
+    }
+    let settings: Settings = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    *cache = Some((path, modified, settings.clone()));
+    settings
+}
+
+fn env_flag(name: &str) -> bool {
+    env::var(name).unwrap_or_default() == "1"
+}
+
+pub fn view() -> SettingsView {
+    let stored = stored();
+    let mut effective = stored.clone();
+    let mut env_overrides = Vec::new();
+    if let Ok(dir) = env::var("HOSTSMANAGER_DATA_DIR") {
+        effective.data_dir = Some(dir);
+        env_overrides.push("data_dir");
+    }
+    if env_flag("HOSTSMANAGER_SAFE_MODE") || env_flag("SWEETHOSTS_SAFE_MODE") {
+        effective.safe_mode = true;
+        env_overrides.push("safe_mode");
+    }
+    if let Ok(p) = env::var("HOSTSMANAGER_SAFE_HOSTS_PATH") {
+        effective.safe_hosts_path = Some(p);
+        env_overrides.push("safe_hosts_path");
+    }
+    SettingsView {
+        effective,
+        stored,
+        env_overrides,
+    }
+}
+
+pub fn effective() -> Settings {
+    view().effective
+}
+
+pub fn save(settings: &Settings) -> Result<(), String> {
+    settings.validate()?;
+    let p = settings_path();
+    if let Some(dir) = p.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let text = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    // dropped either way: a write within the file's timestamp resolution would
+    // not change its modification time
+    let written = fs::write(p, text).map_err(|e| e.to_string());
+    *STORED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    written
+}
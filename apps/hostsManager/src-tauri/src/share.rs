@@ -0,0 +1,228 @@
+// Single rules as portable snippets.
+//
+// A shared rule is its title and content as JSON, deflated and base64url
+// encoded: `hm1.<data>.<check>`, where `check` is the base64url of the first
+// six bytes of the SHA-256 of that JSON. A payload cut short or mangled on the
+// way (chat clients like to) fails the checksum instead of being imported.
+// The payload travels in a `hostsmanager://import?data=<payload>` link or in a
+// text block between `BEGIN` and `END` lines; `decode` takes either, or the bare
+// payload, and ignores whitespace and line breaks inside it.
+//
+// Importing always creates a new rule; it never overwrites one with the same
+// title, which `preview` points out instead.
+use crate::deeplink::{self, Link};
+use crate::hosts::{self, Line};
+use crate::items::{self, ItemError};
+use crate::{tree, validate};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use thiserror::Error;
+
+const VERSION: &str = "hm1";
+const BEGIN: &str = "-----BEGIN HOSTSMANAGER RULE-----";
+const END: &str = "-----END HOSTSMANAGER RULE-----";
+const LINE_WIDTH: usize = 64;
+const CHECK_LEN: usize = 6;
+/// Largest content accepted on import, after decompression.
+pub const MAX_CONTENT: usize = 4 << 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SharedRule {
+    pub title: String,
+    pub content: String,
+}
+
+/// The ways to hand a rule on.
+#[derive(Serialize, Debug, Clone)]
+pub struct Share {
+    pub payload: String,
+    pub link: String,
+    pub text: String,
+}
+
+/// What an import would create.
+#[derive(Serialize, Debug, Clone)]
+pub struct Preview {
+    pub title: String,
+    pub content: String,
+    pub entries: usize,
+    pub warnings: Vec<validate::Warning>,
+    /// Ids of rules that already have this title.
+    pub existing: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum ShareError {
+    #[error("not a shared rule: {0}")]
+    Malformed(String),
+    #[error("shared rule format {0} is not supported")]
+    Version(String),
+    #[error("the shared rule is damaged (checksum mismatch)")]
+    Checksum,
+    #[error("the shared rule is larger than {} bytes", MAX_CONTENT)]
+    TooLarge,
+    #[error("{0} is a folder; only rules can be shared")]
+    Folder(String),
+    #[error(transparent)]
+    Item(#[from] ItemError),
+}
+
+impl ShareError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ShareError::Malformed(_) => "malformed",
+            ShareError::Version(_) => "version",
+            ShareError::Checksum => "checksum",
+            ShareError::TooLarge => "too_large",
+            ShareError::Folder(_) => "folder",
+            ShareError::Item(e) => e.code(),
+        }
+    }
+}
+
+impl Serialize for ShareError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ShareError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+fn check(json: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(&Sha256::digest(json)[..CHECK_LEN])
+}
+
+pub fn encode(rule: &SharedRule) -> String {
+    let json = serde_json::to_vec(rule).unwrap_or_default();
+    let mut deflate = DeflateEncoder::new(Vec::new(), Compression::best());
+    // writing to a Vec cannot fail
+    let _ = deflate.write_all(&json);
+    let data = deflate.finish().unwrap_or_default();
+    format!(
+        "{}.{}.{}",
+        VERSION,
+        URL_SAFE_NO_PAD.encode(data),
+        check(&json)
+    )
+}
+
+/// The payload as a deep link.
+pub fn link(payload: &str) -> String {
+    format!("{}://import?data={}", deeplink::scheme(), payload)
+}
+
+/// The payload as a block of short lines, for pasting where links get cut.
+pub fn text_block(payload: &str) -> String {
+    let mut out = format!("{}\n", BEGIN);
+    for chunk in payload.as_bytes().chunks(LINE_WIDTH) {
+        out.push_str(&String::from_utf8_lossy(chunk));
+        out.push('\n');
+    }
+    out.push_str(END);
+    out.push('\n');
+    out
+}
+
+// The bare payload from a link, a text block or the payload itself.
+fn payload_of(text: &str) -> Result<String, ShareError> {
+    let text = text.trim();
+    if text.contains("://") {
+        return match deeplink::parse(text) {
+            Ok(Link::Import { data }) => Ok(data.split_whitespace().collect()),
+            Ok(_) => Err(ShareError::Malformed("not an import link".to_string())),
+            Err(e) => Err(ShareError::Malformed(e.to_string())),
+        };
+    }
+    let body = match (text.find(BEGIN), text.find(END)) {
+        (Some(start), Some(end)) if start < end => &text[start + BEGIN.len()..end],
+        (None, None) => text,
+        _ => return Err(ShareError::Malformed("incomplete text block".to_string())),
+    };
+    Ok(body.split_whitespace().collect())
+}
+
+/// Parses and verifies a shared rule in any of the forms `Share` offers.
+pub fn decode(text: &str) -> Result<SharedRule, ShareError> {
+    let payload = payload_of(text)?;
+    let mut parts = payload.split('.');
+    let (Some(version), Some(data), Some(sum), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ShareError::Malformed(
+            "expected hm1.<data>.<check>".to_string(),
+        ));
+    };
+    if version != VERSION {
+        return Err(ShareError::Version(version.to_string()));
+    }
+    let data = URL_SAFE_NO_PAD
+        .decode(data)
+        .map_err(|e| ShareError::Malformed(e.to_string()))?;
+    let mut json = Vec::new();
+    // bounded, so a tiny payload cannot inflate into gigabytes
+    DeflateDecoder::new(data.as_slice())
+        .take(MAX_CONTENT as u64 + 1)
+        .read_to_end(&mut json)
+        .map_err(|_| ShareError::Checksum)?;
+    if json.len() > MAX_CONTENT {
+        return Err(ShareError::TooLarge);
+    }
+    if check(&json) != sum {
+        return Err(ShareError::Checksum);
+    }
+    serde_json::from_slice(&json).map_err(|e| ShareError::Malformed(e.to_string()))
+}
+
+/// Shares the rule `id` of `list` with its `content`.
+pub fn share(list: &[Value], id: &str, content: String) -> Result<Share, ShareError> {
+    let item = tree::find(list, id).ok_or_else(|| ItemError::NotFound(id.to_string()))?;
+    if tree::is_folder(item) {
+        return Err(ShareError::Folder(id.to_string()));
+    }
+    let payload = encode(&SharedRule {
+        title: tree::title(item).to_string(),
+        content,
+    });
+    Ok(Share {
+        link: link(&payload),
+        text: text_block(&payload),
+        payload,
+    })
+}
+
+pub fn preview(text: &str, list: &[Value]) -> Result<Preview, ShareError> {
+    let rule = decode(text)?;
+    let entries = hosts::lines(&rule.content)
+        .filter(|(_, raw)| matches!(hosts::parse_line(raw), Line::Entry { .. }))
+        .count();
+    let mut existing = Vec::new();
+    tree::walk(list, &mut |item| {
+        if !tree::is_folder(item) && tree::title(item) == rule.title {
+            existing.extend(tree::id(item).map(String::from));
+        }
+    });
+    Ok(Preview {
+        warnings: validate::validate(&rule.content),
+        title: rule.title,
+        content: rule.content,
+        entries,
+        existing,
+    })
+}
+
+/// Adds the shared rule as a new, disabled rule under `parent` (top level for
+/// `None`) and returns it.
+pub fn import(text: &str, parent: Option<&str>) -> Result<Value, ShareError> {
+    let rule = decode(text)?;
+    let item = json!({ "name": rule.title, "type": "file", "on": false });
+    Ok(items::create(parent, item, None, Some(&rule.content))?)
+}
@@ -0,0 +1,187 @@
+// Serialized writes to the data directory.
+//
+// Every writer of `list.json` or a rule content file holds `lock()`: an
+// in-process mutex (all windows, the deep-link handler and the scheduler share
+// one process) plus an exclusive OS lock on `.lock` in the data directory, so a
+// second process such as a CLI waits as well. Files are replaced through a
+// temporary file and a rename, so readers never need the lock and never see a
+// half-written file.
+//
+// `list.json` has no version field. Its revision is the SHA-256 of the file as
+// stored, which makes an edit from outside the app a new revision too.
+// Listeners registered with `on_list_changed` (the tray menu, the UI event)
+// run after each list write. Every write is checked against the rule flags
+// (see `flags`) of the list it replaces.
+use crate::commands;
+use crate::flags::{self, FlagError};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+
+static PROCESS_LOCK: Mutex<()> = Mutex::new(());
+
+type Listener = Arc<dyn Fn() + Send + Sync>;
+static LIST_LISTENERS: Mutex<Vec<Listener>> = Mutex::new(Vec::new());
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("the list was changed elsewhere (expected revision {expected}, found {found})")]
+    Conflict { expected: String, found: String },
+    #[error(transparent)]
+    Flag(#[from] FlagError),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+// Sent to the UI as `{ code, message }` (plus the current `revision` on a
+// conflict, the `rule_id` on a flag violation) so it can tell a stale write
+// from a broken disk.
+impl Serialize for StoreError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("StoreError", 4)?;
+        match self {
+            StoreError::Conflict { found, .. } => {
+                s.serialize_field("code", "conflict")?;
+                s.serialize_field("revision", found)?;
+                s.skip_field("rule_id")?;
+            }
+            StoreError::Flag(e) => {
+                s.serialize_field("code", e.code())?;
+                s.skip_field("revision")?;
+                s.serialize_field("rule_id", e.rule_id())?;
+            }
+            StoreError::Io(_) => {
+                s.serialize_field("code", "io")?;
+                s.skip_field("revision")?;
+                s.skip_field("rule_id")?;
+            }
+        }
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+/// Held while writing; dropping it releases both locks.
+pub struct Guard {
+    _file: File,
+    _process: MutexGuard<'static, ()>,
+}
+
+pub fn lock() -> io::Result<Guard> {
+    // a panic while holding the mutex leaves no state behind worth protecting
+    let process = PROCESS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    commands::ensure_data_dir()?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(commands::data_dir().join(".lock"))?;
+    file.lock()?;
+    Ok(Guard {
+        _file: file,
+        _process: process,
+    })
+}
+
+pub fn list_path() -> PathBuf {
+    commands::data_dir().join("list.json")
+}
+
+pub fn revision_of(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Revision of `list.json` as it is on disk now; a missing file has the
+/// revision of empty content.
+pub fn list_revision() -> String {
+    revision_of(&fs::read(list_path()).unwrap_or_default())
+}
+
+/// Reads `list.json`, lets `f` change it and writes it back, all under the
+/// lock. A list that does not parse is an error rather than an empty list, so
+/// a damaged file is never silently replaced.
+pub fn update_list<T, E: From<StoreError>>(
+    f: impl FnOnce(&mut Vec<Value>) -> Result<T, E>,
+) -> Result<T, E> {
+    let out = {
+        let _lock = lock().map_err(StoreError::from)?;
+        let mut list: Vec<Value> = match fs::read(list_path()) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| StoreError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(StoreError::Io(e).into()),
+        };
+        let before = list.clone();
+        let out = f(&mut list)?;
+        flags::check_list(&before, &list).map_err(StoreError::from)?;
+        let bytes = serde_json::to_vec(&list).map_err(|e| StoreError::Io(io::Error::other(e)))?;
+        write_atomic(&list_path(), &bytes).map_err(StoreError::from)?;
+        out
+    };
+    notify_list_changed();
+    Ok(out)
+}
+
+/// Replaces the whole list and returns its new revision. With `expected` the
+/// write is rejected when the stored list is no longer at that revision.
+pub fn save_list(list: &[Value], expected: Option<String>) -> Result<String, StoreError> {
+    let revision = {
+        let _lock = lock()?;
+        let stored = fs::read(list_path()).unwrap_or_default();
+        if let Some(expected) = expected {
+            let found = revision_of(&stored);
+            if found != expected {
+                return Err(StoreError::Conflict { expected, found });
+            }
+        }
+        // a damaged list has no flags left to protect
+        let before: Vec<Value> = serde_json::from_slice(&stored).unwrap_or_default();
+        flags::check_list(&before, list)?;
+        let bytes = serde_json::to_vec(list).map_err(io::Error::other)?;
+        write_atomic(&list_path(), &bytes)?;
+        revision_of(&bytes)
+    };
+    notify_list_changed();
+    Ok(revision)
+}
+
+/// Runs `f` after every successful write of `list.json` by this process, once
+/// the lock is released.
+pub fn on_list_changed(f: impl Fn() + Send + Sync + 'static) {
+    LIST_LISTENERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Arc::new(f));
+}
+
+pub(crate) fn notify_list_changed() {
+    // called outside the mutex, so a listener may take its time (or register
+    // another) without holding up every other writer
+    let listeners = LIST_LISTENERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    for f in listeners {
+        f();
+    }
+}
+
+/// Replaces `path` with `bytes` through a temporary file in the same directory.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
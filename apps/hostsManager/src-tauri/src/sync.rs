@@ -0,0 +1,723 @@
+// Optional sync of the rules through git.
+//
+// With `sync_enabled` the data directory doubles as a git repository holding
+// `list.json`, `variables.json` and the rule content files (a `.gitignore`
+// keeps everything else out). Every change made through the app is committed
+// with a message naming the rule (`record`; a run of edits of one rule's
+// content becomes a single commit through `record_edit`), and `pull` / `push`
+// exchange commits with the `main` branch of `sync_remote`, which can be any
+// URL or path git understands. The `git` executable has to be installed.
+//
+// `list.json` is a single line, so git cannot merge two edits of it. `pull`
+// merges it per rule instead: against the common ancestor, a rule changed on
+// one side only takes that side's version, and a rule changed on both sides is
+// a conflict, as is a folder deleted on one side that gained or changed rules
+// on the other. Content files are merged by git. When anything conflicts the
+// pull changes nothing and returns the rules concerned; pulling again with
+// `prefer` resolves every conflict in favour of one side. The working files
+// are only ever replaced by a fast-forward to a complete merge commit, so a
+// conflict cannot leave a half-merged `list.json` behind.
+use crate::{commands, settings, store, tree};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub const BRANCH: &str = "main";
+const LIST_FILE: &str = "list.json";
+const IGNORE: &str = "/*\n!/.gitignore\n!/list.json\n!/variables.json\n!/hosts_content_*.txt\n";
+// identity for commits when git has none configured
+const NAME: &str = "hostsManager";
+const EMAIL: &str = "hostsmanager@localhost";
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("sync is not enabled")]
+    Disabled,
+    #[error("no sync remote is configured")]
+    NoRemote,
+    #[error("the remote has changes that are not pulled yet")]
+    Behind,
+    #[error("{} rule(s) changed both here and on the remote", .0.len())]
+    Conflict(Vec<Conflict>),
+    #[error("git: {0}")]
+    Git(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl SyncError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SyncError::Disabled => "disabled",
+            SyncError::NoRemote => "no_remote",
+            SyncError::Behind => "behind",
+            SyncError::Conflict(_) => "conflict",
+            SyncError::Git(_) => "git",
+            SyncError::Io(_) => "io",
+        }
+    }
+}
+
+// Sent to the UI as `{ code, message }`, plus `conflicts` on a conflict.
+impl Serialize for SyncError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("SyncError", 3)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        match self {
+            SyncError::Conflict(conflicts) => s.serialize_field("conflicts", conflicts)?,
+            _ => s.skip_field("conflicts")?,
+        }
+        s.end()
+    }
+}
+
+/// A rule (or another synced file) changed on both sides.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// `None` for files that belong to no rule, such as `variables.json`.
+    pub rule_id: Option<String>,
+    pub title: String,
+    /// The file that could not be merged: `list.json` when the rule's
+    /// settings differ, its content file when the content does.
+    pub file: String,
+}
+
+/// Which side wins the conflicts of a pull.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Prefer {
+    Local,
+    Remote,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pulled {
+    UpToDate,
+    FastForward,
+    Merged,
+}
+
+fn command(args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(commands::data_dir())
+        .args(["-c", "commit.gpgsign=false", "-c", "core.autocrlf=false"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        // a surrounding repository (say, a hook running the app) is not ours
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env_remove("GIT_INDEX_FILE")
+        .stdin(Stdio::null());
+    cmd
+}
+
+fn checked(out: Output) -> Result<String, SyncError> {
+    if out.status.success() {
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    } else {
+        Err(SyncError::Git(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ))
+    }
+}
+
+fn git(args: &[&str]) -> Result<String, SyncError> {
+    checked(command(args).output()?)
+}
+
+// For commands that answer with their exit status.
+fn git_ok(args: &[&str]) -> Result<bool, SyncError> {
+    Ok(command(args).output()?.status.success())
+}
+
+// Runs against `index` instead of the repository's index.
+fn git_index(index: &Path, args: &[&str]) -> Result<String, SyncError> {
+    checked(command(args).env("GIT_INDEX_FILE", index).output()?)
+}
+
+// Stores `bytes` as a blob and returns its id.
+fn write_blob(bytes: &[u8]) -> Result<String, SyncError> {
+    let mut child = command(&["hash-object", "-w", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("no stdin"))?
+        .write_all(bytes)?;
+    Ok(checked(child.wait_with_output()?)?.trim().to_string())
+}
+
+// The contents of `file` at `rev`, `None` when it does not exist there.
+fn show(rev: &str, file: &str) -> Result<Option<Vec<u8>>, SyncError> {
+    let out = command(&["cat-file", "blob", &format!("{}:{}", rev, file)]).output()?;
+    Ok(out.status.success().then_some(out.stdout))
+}
+
+fn list_at(rev: Option<&str>) -> Result<Vec<Value>, SyncError> {
+    let Some(rev) = rev else {
+        return Ok(vec![]);
+    };
+    match show(rev, LIST_FILE)? {
+        Some(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| SyncError::Git(format!("{} at {} does not parse: {}", LIST_FILE, rev, e))),
+        None => Ok(vec![]),
+    }
+}
+
+// Turns the data directory into a repository with an initial commit of
+// whatever is there. Caller holds the store lock.
+fn ensure_repo() -> Result<(), SyncError> {
+    let dir = commands::data_dir();
+    if dir.join(".git").exists() {
+        return Ok(());
+    }
+    commands::ensure_data_dir()?;
+    git(&["init", "-q", "-b", BRANCH])?;
+    fs::write(dir.join(".gitignore"), IGNORE)?;
+    if !git_ok(&["config", "user.email"])? {
+        git(&["config", "user.name", NAME])?;
+        git(&["config", "user.email", EMAIL])?;
+    }
+    git(&["add", "-A"])?;
+    git(&["commit", "-q", "--allow-empty", "-m", "Start syncing rules"])?;
+    Ok(())
+}
+
+// Commits everything that changed; false when nothing did. Caller holds the
+// store lock.
+fn commit_all(message: &str) -> Result<bool, SyncError> {
+    ensure_repo()?;
+    git(&["add", "-A"])?;
+    if git(&["status", "--porcelain"])?.trim().is_empty() {
+        return Ok(false);
+    }
+    git(&["commit", "-q", "-m", message])?;
+    Ok(true)
+}
+
+/// Commits the data directory with `message` when sync is enabled. Called
+/// after each change of the rules; a failure is logged and the change stands.
+pub fn record(message: &str) {
+    if !settings::effective().sync_enabled {
+        return;
+    }
+    flush_edits();
+    let result = store::lock()
+        .map_err(SyncError::from)
+        .and_then(|_lock| commit_all(message));
+    if let Err(e) = result {
+        eprintln!("sync: cannot commit {:?}: {}", message, e);
+    }
+}
+
+/// How long content edits wait for the next one before they are committed.
+pub const EDIT_IDLE: Duration = Duration::from_secs(3);
+
+// Content edits not committed yet: the file edited, the commit message and
+// when the last edit was made.
+struct PendingEdit {
+    file: PathBuf,
+    message: String,
+    at: Instant,
+}
+
+static PENDING: Mutex<Option<PendingEdit>> = Mutex::new(None);
+
+fn pending() -> MutexGuard<'static, Option<PendingEdit>> {
+    PENDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Like `record`, for the content edits the editor saves on every keystroke:
+/// a run of edits of the rule `id` becomes one commit of its content file,
+/// made once no edit followed for `EDIT_IDLE`, or earlier when anything else
+/// is committed, pulled or pushed.
+pub fn record_edit(id: &str, message: &str) {
+    if !settings::effective().sync_enabled {
+        return;
+    }
+    let file = commands::content_path(id);
+    let mut slot = pending();
+    let previous = match slot.take() {
+        Some(p) if p.file == file => {
+            *slot = Some(PendingEdit {
+                message: message.to_string(),
+                at: Instant::now(),
+                ..p
+            });
+            return;
+        }
+        previous => previous,
+    };
+    let waiting = previous.is_some();
+    *slot = Some(PendingEdit {
+        file,
+        message: message.to_string(),
+        at: Instant::now(),
+    });
+    drop(slot);
+    if let Some(p) = previous {
+        commit_edit(p);
+    }
+    // the thread that waited for the previous edit waits for this one
+    if !waiting {
+        std::thread::spawn(wait_for_idle);
+    }
+}
+
+fn wait_for_idle() {
+    loop {
+        let mut slot = pending();
+        let Some(elapsed) = slot.as_ref().map(|p| p.at.elapsed()) else {
+            return;
+        };
+        if elapsed < EDIT_IDLE {
+            drop(slot);
+            std::thread::sleep(EDIT_IDLE - elapsed);
+            continue;
+        }
+        let idle = slot.take();
+        drop(slot);
+        if let Some(p) = idle {
+            commit_edit(p);
+        }
+        return;
+    }
+}
+
+// Commits only the edited file, so a change made since (say, to `list.json`)
+// keeps its own message. An edit in a data directory that is no longer the
+// current one is left for the next commit there.
+fn commit_edit(p: PendingEdit) {
+    if p.file.parent() != Some(commands::data_dir().as_path()) {
+        return;
+    }
+    let file = p.file.to_string_lossy();
+    let result = store::lock().map_err(SyncError::from).and_then(|_lock| {
+        ensure_repo()?;
+        git(&["add", "--", &file])?;
+        if !git(&["status", "--porcelain", "--", &file])?
+            .trim()
+            .is_empty()
+        {
+            git(&["commit", "-q", "-m", &p.message, "--", &file])?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("sync: cannot commit {:?}: {}", p.message, e);
+    }
+}
+
+/// Commits the content edits `record_edit` is holding back. Called before
+/// other commits, pulls and pushes, and on quit.
+pub fn flush_edits() {
+    let taken = pending().take();
+    if let Some(p) = taken {
+        commit_edit(p);
+    }
+}
+
+/// The title of `item`, or its id when it has none.
+pub fn label(item: &Value) -> &str {
+    match tree::title(item) {
+        "" => tree::id(item).unwrap_or("?"),
+        title => title,
+    }
+}
+
+/// `Enable <rule>`, `Disable <rule>` or `Edit <rule>` for a change of
+/// `before` into `after`.
+pub fn describe_edit(before: &Value, after: &Value) -> String {
+    let without_on = |item: &Value| {
+        let mut item = item.clone();
+        if let Some(obj) = item.as_object_mut() {
+            obj.remove("on");
+        }
+        item
+    };
+    let verb = match (tree::is_on(before), tree::is_on(after)) {
+        (false, true) if without_on(before) == without_on(after) => "Enable",
+        (true, false) if without_on(before) == without_on(after) => "Disable",
+        _ => "Edit",
+    };
+    format!("{} {}", verb, label(after))
+}
+
+/// A commit message for replacing the rule tree `before` with `after`.
+pub fn describe_list_change(before: &[Value], after: &[Value]) -> String {
+    let old = flatten(before);
+    let new = flatten(after);
+    let mut changes = Vec::new();
+    for (id, flat) in &new.items {
+        match old.get(id) {
+            None => changes.push(format!("Add {}", label(&flat.item))),
+            Some(prev) if prev.item != flat.item => {
+                changes.push(describe_edit(&prev.item, &flat.item))
+            }
+            _ => {}
+        }
+    }
+    for (id, flat) in &old.items {
+        if new.get(id).is_none() {
+            changes.push(format!("Delete {}", label(&flat.item)));
+        }
+    }
+    match changes.len() {
+        0 => "Reorder rules".to_string(),
+        1..=3 => changes.join(", "),
+        n => format!("{} and {} more", changes[..3].join(", "), n - 3),
+    }
+}
+
+// An item without its children and where it sits in the tree.
+#[derive(Debug, Clone)]
+struct Flat {
+    item: Value,
+    parent: Option<String>,
+    index: usize,
+}
+
+impl Flat {
+    fn same_place(&self, other: &Flat) -> bool {
+        self.parent == other.parent && self.index == other.index
+    }
+}
+
+// Every item of a tree by id, in tree order.
+#[derive(Default)]
+struct FlatTree {
+    items: Vec<(String, Flat)>,
+    by_id: HashMap<String, usize>,
+}
+
+impl FlatTree {
+    fn get(&self, id: &str) -> Option<&Flat> {
+        self.by_id.get(id).map(|&i| &self.items[i].1)
+    }
+}
+
+fn flatten(list: &[Value]) -> FlatTree {
+    fn walk(items: &[Value], parent: Option<&str>, out: &mut FlatTree) {
+        for (index, item) in items.iter().enumerate() {
+            let Some(id) = tree::id(item) else {
+                continue;
+            };
+            let mut bare = item.clone();
+            if let Some(obj) = bare.as_object_mut() {
+                obj.remove("children");
+            }
+            out.by_id.insert(id.to_string(), out.items.len());
+            out.items.push((
+                id.to_string(),
+                Flat {
+                    item: bare,
+                    parent: parent.map(str::to_string),
+                    index,
+                },
+            ));
+            if let Some(children) = tree::children(item) {
+                walk(children, Some(id), out);
+            }
+        }
+    }
+    let mut out = FlatTree::default();
+    walk(list, None, &mut out);
+    out
+}
+
+// Replaces the fields of the item `id` in `list` with those of `bare`,
+// keeping its children.
+fn set_fields(list: &mut [Value], id: &str, bare: &Value) {
+    let Some(item) = tree::find_mut(list, id) else {
+        return;
+    };
+    let children = item.get("children").cloned();
+    *item = bare.clone();
+    if let Some(children) = children {
+        item["children"] = children;
+    } else if tree::is_folder(item) {
+        item["children"] = json!([]);
+    }
+}
+
+// Puts `item` into `parent` (the top level when it is gone) at `index`.
+fn place(list: &mut Vec<Value>, parent: Option<&str>, index: usize, mut item: Value) {
+    if tree::is_folder(&item) && tree::children(&item).is_none() {
+        item["children"] = json!([]);
+    }
+    let folder = parent
+        .and_then(|p| tree::find_mut(list, p))
+        .and_then(tree::children_mut);
+    if let Some(children) = folder {
+        let index = index.min(children.len());
+        children.insert(index, item);
+        return;
+    }
+    let index = index.min(list.len());
+    list.insert(index, item);
+}
+
+// Whether anything below the folder `id` in `ours` was added, edited or moved
+// since `base`; deleting the folder would take it along.
+fn changed_below(ours: &[Value], id: &str, b: &FlatTree, o: &FlatTree) -> bool {
+    let Some(folder) = tree::find(ours, id) else {
+        return false;
+    };
+    tree::subtree_ids(folder)
+        .iter()
+        .skip(1)
+        .any(|child| match (b.get(child), o.get(child)) {
+            (Some(base), Some(mine)) => base.item != mine.item || !base.same_place(mine),
+            _ => true,
+        })
+}
+
+// Merges the rule trees per rule. `ours` is the starting point; the changes
+// `theirs` made since `base` are applied to it. Rules changed on both sides
+// are returned as conflicts, or settled by `prefer`.
+fn merge_lists(
+    base: &[Value],
+    ours: &[Value],
+    theirs: &[Value],
+    prefer: Option<Prefer>,
+) -> (Vec<Value>, Vec<Conflict>) {
+    let (b, o, t) = (flatten(base), flatten(ours), flatten(theirs));
+    let mut out = ours.to_vec();
+    let mut conflicts = Vec::new();
+    let take_theirs = prefer == Some(Prefer::Remote);
+    let mut conflict = |id: &str, item: &Value| {
+        if prefer.is_none() {
+            conflicts.push(Conflict {
+                rule_id: Some(id.to_string()),
+                title: label(item).to_string(),
+                file: LIST_FILE.to_string(),
+            });
+        }
+    };
+
+    // deletions first, so moves and additions see the final folders
+    for (id, base_item) in &b.items {
+        match (o.get(id), t.get(id)) {
+            (Some(mine), None) => {
+                let untouched = mine.item == base_item.item && !changed_below(ours, id, &b, &o);
+                if untouched || take_theirs {
+                    tree::take(&mut out, id);
+                } else {
+                    conflict(id, &mine.item);
+                }
+            }
+            (None, Some(other)) if other.item != base_item.item => {
+                if take_theirs {
+                    place(
+                        &mut out,
+                        other.parent.as_deref(),
+                        other.index,
+                        other.item.clone(),
+                    );
+                } else {
+                    conflict(id, &other.item);
+                }
+            }
+            _ => {}
+        }
+    }
+    for (id, other) in &t.items {
+        match (b.get(id), o.get(id)) {
+            (None, None) => place(
+                &mut out,
+                other.parent.as_deref(),
+                other.index,
+                other.item.clone(),
+            ),
+            (base_item, Some(mine)) => {
+                let base_fields = base_item.map(|f| &f.item);
+                if other.item != mine.item && base_fields != Some(&other.item) {
+                    if base_fields == Some(&mine.item) || take_theirs {
+                        set_fields(&mut out, id, &other.item);
+                    } else {
+                        conflict(id, &mine.item);
+                    }
+                }
+                let moved = base_item.is_some_and(|f| !f.same_place(other) && f.same_place(mine));
+                if moved {
+                    if let Some(item) = tree::take(&mut out, id) {
+                        place(&mut out, other.parent.as_deref(), other.index, item);
+                    }
+                }
+            }
+            // deleted here, settled above
+            (Some(_), None) => {}
+        }
+    }
+    (out, conflicts)
+}
+
+// The rule a synced file belongs to, for reporting a conflict.
+fn conflict_for(file: &str, ours: &[Value], theirs: &[Value]) -> Conflict {
+    let rule_id = file
+        .strip_prefix("hosts_content_")
+        .and_then(|f| f.strip_suffix(".txt"));
+    let title = rule_id
+        .and_then(|id| tree::find(ours, id).or_else(|| tree::find(theirs, id)))
+        .map(|item| label(item).to_string())
+        .unwrap_or_else(|| file.to_string());
+    Conflict {
+        rule_id: rule_id.map(str::to_string),
+        title,
+        file: file.to_string(),
+    }
+}
+
+fn remote() -> Result<String, SyncError> {
+    let s = settings::effective();
+    if !s.sync_enabled {
+        return Err(SyncError::Disabled);
+    }
+    s.sync_remote.ok_or(SyncError::NoRemote)
+}
+
+/// Merges the remote's rules into the local ones, committing local changes
+/// first. On conflicts nothing changes unless `prefer` picks a side.
+pub fn pull(prefer: Option<Prefer>) -> Result<Pulled, SyncError> {
+    let remote = remote()?;
+    flush_edits();
+    let pulled = {
+        let _lock = store::lock()?;
+        commit_all("Save local changes")?;
+        if git(&["ls-remote", &remote, &format!("refs/heads/{}", BRANCH)])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(Pulled::UpToDate);
+        }
+        git(&["fetch", "-q", &remote, BRANCH])?;
+        if git_ok(&["merge-base", "--is-ancestor", "FETCH_HEAD", "HEAD"])? {
+            return Ok(Pulled::UpToDate);
+        }
+        if git_ok(&["merge-base", "--is-ancestor", "HEAD", "FETCH_HEAD"])? {
+            git(&["merge", "-q", "--ff-only", "FETCH_HEAD"])?;
+            Pulled::FastForward
+        } else {
+            merge(prefer)?;
+            Pulled::Merged
+        }
+    };
+    store::notify_list_changed();
+    Ok(pulled)
+}
+
+// Merges FETCH_HEAD into HEAD, which have diverged.
+fn merge(prefer: Option<Prefer>) -> Result<(), SyncError> {
+    // histories are unrelated when both sides started syncing on their own
+    let base = git(&["merge-base", "HEAD", "FETCH_HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string());
+    let out = command(&[
+        "merge-tree",
+        "--write-tree",
+        "--name-only",
+        "--no-messages",
+        "--allow-unrelated-histories",
+        "HEAD",
+        "FETCH_HEAD",
+    ])
+    .output()?;
+    // exit status 1 means conflicts; the tree is still written
+    if !matches!(out.status.code(), Some(0) | Some(1)) {
+        return Err(SyncError::Git(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+    let mut lines = stdout.lines();
+    let merged_tree = lines.next().unwrap_or_default().to_string();
+    let conflicted: Vec<&str> = lines
+        .take_while(|l| !l.is_empty())
+        .filter(|&f| f != LIST_FILE)
+        .collect();
+
+    let ours = list_at(Some("HEAD"))?;
+    let theirs = list_at(Some("FETCH_HEAD"))?;
+    let (list, mut conflicts) = merge_lists(&list_at(base.as_deref())?, &ours, &theirs, prefer);
+
+    let index = commands::data_dir().join(".git").join("sync-index");
+    let _ = fs::remove_file(&index);
+    let result = (|| {
+        git_index(&index, &["read-tree", &merged_tree])?;
+        let blob = write_blob(&serde_json::to_vec(&list).map_err(io::Error::other)?)?;
+        let entry = format!("100644,{},{}", blob, LIST_FILE);
+        git_index(&index, &["update-index", "--add", "--cacheinfo", &entry])?;
+        for file in &conflicted {
+            let Some(prefer) = prefer else {
+                conflicts.push(conflict_for(file, &ours, &theirs));
+                continue;
+            };
+            let side = match prefer {
+                Prefer::Local => "HEAD",
+                Prefer::Remote => "FETCH_HEAD",
+            };
+            match git(&["rev-parse", "-q", "--verify", &format!("{}:{}", side, file)]) {
+                Ok(blob) => {
+                    let entry = format!("100644,{},{}", blob.trim(), file);
+                    git_index(&index, &["update-index", "--add", "--cacheinfo", &entry])?;
+                }
+                Err(_) => {
+                    git_index(&index, &["update-index", "--force-remove", file])?;
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(SyncError::Conflict(std::mem::take(&mut conflicts)));
+        }
+        git_index(&index, &["write-tree"])
+    })();
+    let _ = fs::remove_file(&index);
+    let tree = result?;
+
+    let commit = git(&[
+        "commit-tree",
+        tree.trim(),
+        "-p",
+        "HEAD",
+        "-p",
+        "FETCH_HEAD",
+        "-m",
+        "Merge remote rules",
+    ])?;
+    git(&["merge", "-q", "--ff-only", commit.trim()])?;
+    Ok(())
+}
+
+/// Sends the local commits to the remote. Fails with `Behind` when the remote
+/// has commits that have to be pulled first.
+pub fn push() -> Result<(), SyncError> {
+    let remote = remote()?;
+    flush_edits();
+    let _lock = store::lock()?;
+    commit_all("Save local changes")?;
+    let out = command(&[
+        "push",
+        "-q",
+        &remote,
+        &format!("HEAD:refs/heads/{}", BRANCH),
+    ])
+    .output()?;
+    if !out.status.success() && String::from_utf8_lossy(&out.stderr).contains("rejected") {
+        return Err(SyncError::Behind);
+    }
+    checked(out).map(|_| ())
+}
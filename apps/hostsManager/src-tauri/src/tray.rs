@@ -0,0 +1,230 @@
+// Tray icon whose menu mirrors the rule tree.
+//
+// Every rule is a checkable entry and every folder a submenu, followed by a
+// "profiles" submenu with one radio-style entry per top-level folder (see
+// `items::activate_profile`), then "apply", "open editor" and "quit". System
+// rules are shown but cannot be toggled. The menu is rebuilt after each write
+// of `list.json` (see `store::on_list_changed`), whoever made it. Toggling a
+// rule is the same edit the UI makes, `items::update` and then applying the
+// system hosts file; a failed write is reported as a notification because the
+// window may well be hidden.
+use crate::{apply, audit, commands, flags, items, tree};
+use serde_json::{json, Map, Value};
+use std::thread;
+use tauri::menu::{
+    CheckMenuItem, Menu, MenuEvent, MenuItem, MenuItemKind, PredefinedMenuItem, Submenu,
+};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_notification::NotificationExt;
+
+const TRAY_ID: &str = "main";
+const RULE_PREFIX: &str = "rule:";
+const PROFILE_PREFIX: &str = "profile:";
+const APPLY: &str = "apply";
+const OPEN: &str = "open";
+const QUIT: &str = "quit";
+
+fn rule_entries(app: &AppHandle, items: &[Value]) -> tauri::Result<Vec<MenuItemKind<Wry>>> {
+    let mut out = Vec::new();
+    for item in items {
+        let Some(id) = tree::id(item) else {
+            continue;
+        };
+        let title = title_or_id(item);
+        if tree::is_folder(item) {
+            let sub = Submenu::new(app, title, true)?;
+            for child in rule_entries(app, tree::children(item).map_or(&[], |c| c))? {
+                sub.append(&child)?;
+            }
+            out.push(MenuItemKind::Submenu(sub));
+        } else {
+            out.push(MenuItemKind::Check(CheckMenuItem::with_id(
+                app,
+                format!("{}{}", RULE_PREFIX, id),
+                title,
+                !flags::is_system(item),
+                tree::is_on(item),
+                None::<&str>,
+            )?));
+        }
+    }
+    Ok(out)
+}
+
+fn title_or_id(item: &Value) -> &str {
+    match tree::title(item) {
+        "" => tree::id(item).unwrap_or_default(),
+        t => t,
+    }
+}
+
+// One entry per top-level folder, the active one checked; `None` without
+// folders.
+fn profile_menu(app: &AppHandle, list: &[Value]) -> tauri::Result<Option<Submenu<Wry>>> {
+    let active = items::active_profile(list);
+    let mut profiles = items::profiles(list).peekable();
+    if profiles.peek().is_none() {
+        return Ok(None);
+    }
+    let sub = Submenu::new(app, "配置", true)?;
+    for folder in profiles {
+        let Some(id) = tree::id(folder) else {
+            continue;
+        };
+        sub.append(&CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROFILE_PREFIX, id),
+            title_or_id(folder),
+            true,
+            active == Some(id),
+            None::<&str>,
+        )?)?;
+    }
+    Ok(Some(sub))
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    let list = commands::get_list();
+    let rules = rule_entries(app, &list)?;
+    for entry in &rules {
+        menu.append(entry)?;
+    }
+    if !rules.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    if let Some(profiles) = profile_menu(app, &list)? {
+        menu.append(&profiles)?;
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    menu.append(&MenuItem::with_id(app, APPLY, "应用", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        OPEN,
+        "打开编辑器",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(app, QUIT, "退出", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Hosts Manager")
+        .menu(&build_menu(app)?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(on_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuilds the menu from the current list. The rebuild is queued on the main
+/// thread and not waited for: menus are built there, and a writer on another
+/// thread waiting for it could deadlock against a command on the main thread.
+pub fn refresh(app: &AppHandle) {
+    let handle = app.clone();
+    let queued = app.run_on_main_thread(move || {
+        let Some(tray) = handle.tray_by_id(TRAY_ID) else {
+            return;
+        };
+        if let Err(e) = build_menu(&handle).and_then(|menu| tray.set_menu(Some(menu))) {
+            eprintln!("hostsmanager: cannot rebuild tray menu: {}", e);
+        }
+    });
+    if let Err(e) = queued {
+        eprintln!("hostsmanager: cannot rebuild tray menu: {}", e);
+    }
+}
+
+pub(crate) fn notify_error(app: &AppHandle, body: &str) {
+    let _ = app
+        .notification()
+        .builder()
+        .title("Hosts Manager")
+        .body(body)
+        .show();
+}
+
+// Applies the enabled rules; without a cached password this only succeeds
+// when the hosts file is writable (or in safe mode).
+pub(crate) fn apply_now(app: &AppHandle) {
+    let res = apply::apply_system_hosts(None, audit::Action::Write);
+    if res.get("success").and_then(|v| v.as_bool()) == Some(true) {
+        let _ = app.emit("SYSTEM_HOSTS_UPDATED", res.get("new_content").cloned());
+        return;
+    }
+    notify_apply_failed(app, &res);
+}
+
+/// Reports a failed apply (`set_system_hosts` result) as a notification.
+pub(crate) fn notify_apply_failed(app: &AppHandle, res: &Value) {
+    let reason = res
+        .get("message")
+        .and_then(|v| v.as_str())
+        .filter(|m| !m.trim().is_empty())
+        .or_else(|| res.get("code").and_then(|v| v.as_str()))
+        .unwrap_or("未知错误");
+    notify_error(app, &format!("写入 hosts 失败：{}", reason));
+}
+
+/// Switches the rule on or off and applies, as the UI's switch would.
+pub(crate) fn set_on(app: &AppHandle, id: &str, on: bool) {
+    let mut patch = Map::new();
+    patch.insert("on".to_string(), json!(on));
+    match items::update(id, patch) {
+        Ok(_) => apply_now(app),
+        Err(e) => {
+            notify_error(app, &format!("切换规则失败：{}", e));
+            // a tray click already flipped the native check mark; put it back
+            refresh(app);
+        }
+    }
+}
+
+// Switches to the profile and applies. The native check mark flipped on the
+// click; the rebuild after the list write puts the marks right.
+fn switch_profile(app: &AppHandle, id: &str) {
+    match items::activate_profile(id) {
+        Ok(_) => apply_now(app),
+        Err(e) => {
+            notify_error(app, &format!("切换配置失败：{}", e));
+            refresh(app);
+        }
+    }
+}
+
+fn toggle(app: &AppHandle, id: &str) {
+    let on = tree::find(&commands::get_list(), id).is_some_and(tree::is_on);
+    set_on(app, id, !on);
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref().to_string();
+    let app = app.clone();
+    match id.as_str() {
+        OPEN => {
+            let _ = commands::show_main_window(&app);
+        }
+        QUIT => {
+            commands::shutdown(&app);
+            app.exit(0);
+        }
+        // writes may wait on the store lock; keep the menu responsive
+        APPLY => {
+            thread::spawn(move || apply_now(&app));
+        }
+        _ => {
+            if let Some(rule) = id.strip_prefix(RULE_PREFIX).map(String::from) {
+                thread::spawn(move || toggle(&app, &rule));
+            } else if let Some(profile) = id.strip_prefix(PROFILE_PREFIX).map(String::from) {
+                thread::spawn(move || switch_profile(&app, &profile));
+            }
+        }
+    }
+}
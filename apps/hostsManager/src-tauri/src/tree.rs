@@ -0,0 +1,93 @@
+// Helpers for the rule tree stored in `list.json`.
+//
+// Items stay `serde_json::Value` (see the note at the top of commands.rs); these
+// accessors keep the field-name fallbacks in one place.
+use serde_json::Value;
+
+pub fn id(item: &Value) -> Option<&str> {
+    item.get("id").and_then(|v| v.as_str())
+}
+
+/// Display title; this app stores `name`, SwitchHosts data uses `title`.
+pub fn title(item: &Value) -> &str {
+    item.get("name")
+        .or_else(|| item.get("title"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+}
+
+pub fn is_on(item: &Value) -> bool {
+    item.get("on").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+pub fn children(item: &Value) -> Option<&Vec<Value>> {
+    item.get("children").and_then(|v| v.as_array())
+}
+
+/// Visits every item depth-first in tree order.
+pub fn walk<'a>(items: &'a [Value], f: &mut impl FnMut(&'a Value)) {
+    for item in items {
+        f(item);
+        if let Some(children) = children(item) {
+            walk(children, f);
+        }
+    }
+}
+
+pub fn find<'a>(items: &'a [Value], id: &str) -> Option<&'a Value> {
+    for item in items {
+        if self::id(item) == Some(id) {
+            return Some(item);
+        }
+        if let Some(found) = children(item).and_then(|c| find(c, id)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+pub fn find_mut<'a>(items: &'a mut [Value], id: &str) -> Option<&'a mut Value> {
+    for item in items {
+        if self::id(item) == Some(id) {
+            return Some(item);
+        }
+        if let Some(found) = item
+            .get_mut("children")
+            .and_then(|c| c.as_array_mut())
+            .and_then(|c| find_mut(c, id))
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
+pub fn children_mut(item: &mut Value) -> Option<&mut Vec<Value>> {
+    item.get_mut("children").and_then(|v| v.as_array_mut())
+}
+
+pub fn is_folder(item: &Value) -> bool {
+    item.get("type").and_then(|v| v.as_str()) == Some("folder")
+}
+
+/// Removes the item with `id` wherever it is in the tree and returns it.
+pub fn take(items: &mut Vec<Value>, id: &str) -> Option<Value> {
+    if let Some(i) = items.iter().position(|item| self::id(item) == Some(id)) {
+        return Some(items.remove(i));
+    }
+    items
+        .iter_mut()
+        .filter_map(children_mut)
+        .find_map(|children| take(children, id))
+}
+
+/// Ids of `item` and everything below it, in tree order.
+pub fn subtree_ids(item: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    walk(std::slice::from_ref(item), &mut |v| {
+        if let Some(id) = id(v) {
+            ids.push(id.to_string());
+        }
+    });
+    ids
+}
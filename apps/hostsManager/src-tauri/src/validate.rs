@@ -0,0 +1,109 @@
+// Lints for hosts content, reported to the editor as warnings.
+//
+// Nothing here blocks saving or applying; the warnings point at lines that are
+// ignored by the system resolver, that block or redirect a hostname to the
+// local machine in one address family only (an IPv6-preferring resolver will
+// bypass a hostname that is only mapped to 127.0.0.1), or that hold
+// wildcard/regex hosts, which a plain hosts file cannot express. Hostnames
+// mapped to other addresses of one family are normal (most LAN and public
+// hosts have no IPv6 address) and are not reported.
+use crate::hosts::{self, Line, Pattern};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub code: &'static str,
+    pub message: String,
+}
+
+// How one address family maps a hostname.
+#[derive(Default)]
+struct Family {
+    mapped: bool,
+    // first line pointing it at a loopback or unspecified address
+    local: Option<usize>,
+}
+
+// Loopback or unspecified: the addresses that block a hostname or keep it on
+// this machine, and so leak when the other family has no counterpart.
+fn is_local(ip: &IpAddr) -> bool {
+    ip.is_loopback() || ip.is_unspecified()
+}
+
+pub fn validate(content: &str) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    // hostname -> (IPv4, IPv6)
+    let mut families: BTreeMap<String, (Family, Family)> = BTreeMap::new();
+
+    for (n, raw) in hosts::lines(content) {
+        match hosts::parse_line(raw) {
+            Line::Invalid(text) => warnings.push(Warning {
+                line: n,
+                code: "invalid_line",
+                message: format!("not a hosts entry: {}", text),
+            }),
+            Line::Entry { ip, hosts, .. } => {
+                for host in hosts {
+                    if hosts::is_pattern(host) {
+                        warnings.push(match Pattern::new(host) {
+                            Ok(_) => Warning {
+                                line: n,
+                                code: "pattern_host",
+                                message: format!(
+                                    "{} is a pattern: it is expanded against the pattern hosts \
+                                     in the hosts file and served as is in resolver mode",
+                                    host
+                                ),
+                            },
+                            Err(message) => Warning {
+                                line: n,
+                                code: "invalid_pattern",
+                                message,
+                            },
+                        });
+                        continue;
+                    }
+                    let entry = families.entry(host.to_ascii_lowercase()).or_default();
+                    let family = if ip.is_ipv4() {
+                        &mut entry.0
+                    } else {
+                        &mut entry.1
+                    };
+                    family.mapped = true;
+                    if is_local(&ip) {
+                        family.local.get_or_insert(n);
+                    }
+                }
+            }
+            Line::Blank | Line::Comment(_) => {}
+        }
+    }
+
+    for (host, (v4, v6)) in families {
+        match (v4.local, v6.local) {
+            (Some(line), _) if !v6.mapped => warnings.push(Warning {
+                line,
+                code: "ipv4_only",
+                message: format!(
+                    "{} points at a local IPv4 address but has no IPv6 mapping (add ::1 or ::)",
+                    host
+                ),
+            }),
+            (_, Some(line)) if !v4.mapped => warnings.push(Warning {
+                line,
+                code: "ipv6_only",
+                message: format!(
+                    "{} points at a local IPv6 address but has no IPv4 mapping (add 127.0.0.1 or 0.0.0.0)",
+                    host
+                ),
+            }),
+            _ => {}
+        }
+    }
+
+    warnings.sort_by_key(|w| w.line);
+    warnings
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use hostsmanager_lib::blocklist::{self, Format};
+use hostsmanager_lib::commands;
+use serde_json::json;
+
+fn allow(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn detects_the_format() {
+    assert_eq!(blocklist::detect(""), Format::Hosts);
+    assert_eq!(
+        blocklist::detect("# list\n0.0.0.0 ads.test\n"),
+        Format::Hosts
+    );
+    assert_eq!(
+        blocklist::detect("! comment\n||ads.test^\n"),
+        Format::Adblock
+    );
+    assert_eq!(blocklist::detect("[Adblock Plus 2.0]\n"), Format::Adblock);
+    assert_eq!(blocklist::detect("ads.test\ntrack.test\n"), Format::Domains);
+    assert_eq!("domains".parse::<Format>().unwrap(), Format::Domains);
+    assert!("csv".parse::<Format>().is_err());
+}
+
+#[test]
+fn hosts_lists_keep_their_addresses() {
+    let text = "# header\n127.0.0.1 localhost\n0.0.0.0 ads.test track.test\n\
+                0.0.0.0 ads.test\n10.0.0.1 cdn.ads.test\nnot an entry\n";
+    let (out, count) = blocklist::convert(text, Format::Hosts, &allow(&["localhost"]));
+    assert_eq!(count, 2, "{}", out);
+    assert!(out.contains("0.0.0.0 ads.test track.test"), "{}", out);
+    assert!(out.contains("10.0.0.1 cdn.ads.test"), "{}", out);
+    assert!(!out.contains("localhost"), "{}", out);
+    assert_eq!(out.matches("ads.test").count(), 2, "{}", out);
+
+    // allowlisting a name drops its subdomains and empties the line
+    let (out, count) = blocklist::convert(text, Format::Hosts, &allow(&["ADS.test"]));
+    assert_eq!(count, 2, "{}", out);
+    assert!(!out.contains("ads.test"), "{}", out);
+    assert!(out.contains("0.0.0.0 track.test"), "{}", out);
+}
+
+#[test]
+fn adblock_lists_block_plain_domain_rules_only() {
+    let text = "[Adblock Plus 2.0]\n! comment\n||Ads.test^\n||track.test^$important\n\
+                ||ads.test^\n||cdn.test/path\n||*.wild.test^\n##.banner\n\
+                @@||ok.track.test^\n||ok.track.test^\n||sub.ok.track.test^\n";
+    let (out, count) = blocklist::convert(text, Format::Adblock, &[]);
+    let lines: Vec<&str> = out.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(lines, ["0.0.0.0 ads.test", "0.0.0.0 track.test"], "{}", out);
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn adblock_rules_scoped_by_options_are_skipped() {
+    let text =
+        "||ads.test^$third-party\n||cdn.test^$script,image\n||web.test^$domain=example.org\n\
+                ||pop.test^$popup\n||all.test^$All,important\n||doc.test^$doc\n\
+                ||ok.test^\n@@||ok.test^$script\n||ok2.test^\n@@||ok2.test^$document\n";
+    let (out, count) = blocklist::convert(text, Format::Adblock, &[]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "0.0.0.0 pop.test",
+            "0.0.0.0 all.test",
+            "0.0.0.0 doc.test",
+            "0.0.0.0 ok.test"
+        ],
+        "{}",
+        out
+    );
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn domain_lists_skip_comments_and_invalid_names() {
+    let text = "# list\nads.test\nTRACK.test # trailing\nads.test\n\nnot a domain\n\
+                -bad.test\nok.allowed.test\n";
+    let (out, count) = blocklist::convert(text, Format::Domains, &allow(&["allowed.test"]));
+    let lines: Vec<&str> = out.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(lines, ["0.0.0.0 ads.test", "0.0.0.0 track.test"], "{}", out);
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn imports_into_a_rule_with_its_allowlist() {
+    let _s = common::sandbox();
+    common::set_list(vec![json!({
+        "id": "b", "name": "Blocklist", "on": true, "allowlist": ["keep.test"]
+    })]);
+    let count = commands::import_blocklist(
+        "b".into(),
+        "||ads.test^\n||keep.test^\n||x.keep.test^\n".into(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(count, 1);
+    let content = commands::get_hosts_content("b".into());
+    assert!(content.contains("0.0.0.0 ads.test"), "{}", content);
+    assert!(!content.contains("keep.test"), "{}", content);
+
+    assert!(
+        commands::import_blocklist("b".into(), "ads.test\n".into(), Some("csv".into())).is_err()
+    );
+    assert!(commands::import_blocklist("missing".into(), "ads.test\n".into(), None).is_err());
+}
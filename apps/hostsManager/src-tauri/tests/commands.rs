@@ -0,0 +1,160 @@
+mod common;
+
+use hostsmanager_lib::{audit, commands};
+use serde_json::json;
+use std::fs;
+
+#[test]
+fn list_round_trip() {
+    let _s = common::sandbox();
+    assert_eq!(commands::get_list(), Vec::<serde_json::Value>::new());
+
+    let list = vec![
+        json!({ "id": "a", "name": "A", "type": "file", "on": true }),
+        json!({ "id": "f", "name": "F", "type": "folder", "children": [
+            { "id": "b", "name": "B", "type": "file", "on": false }
+        ] }),
+    ];
+    assert!(commands::set_list(list.clone(), None).is_ok());
+    assert_eq!(commands::get_list(), list);
+}
+
+#[test]
+fn hosts_content_round_trip() {
+    let _s = common::sandbox();
+    assert!(commands::set_hosts_content("a".into(), "1.1.1.1 a.test\n".into()).unwrap());
+    assert_eq!(commands::get_hosts_content("a".into()), "1.1.1.1 a.test\n");
+}
+
+#[test]
+fn nested_folders_compose_in_tree_order() {
+    let s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "top", "name": "Top", "type": "file", "on": true }),
+        json!({ "id": "f1", "name": "F1", "type": "folder", "children": [
+            { "id": "mid", "name": "Mid", "type": "file", "on": true },
+            { "id": "f2", "name": "F2", "type": "folder", "children": [
+                { "id": "deep", "name": "Deep", "type": "file", "on": true },
+                { "id": "off", "name": "Off", "type": "file", "on": false }
+            ] }
+        ] }),
+    ]);
+    s.write("hosts_content_top.txt", "1.0.0.1 top.test\n");
+    s.write("hosts_content_mid.txt", "1.0.0.2 mid.test\n");
+    s.write("hosts_content_deep.txt", "1.0.0.3 deep.test\n");
+    s.write("hosts_content_off.txt", "1.0.0.4 off.test\n");
+
+    let content = commands::get_content_of_list(None).unwrap();
+    let top = content.find("top.test").unwrap();
+    let mid = content.find("mid.test").unwrap();
+    let deep = content.find("deep.test").unwrap();
+    assert!(top < mid && mid < deep, "{}", content);
+    assert!(!content.contains("off.test"));
+    assert!(content.contains("# --- rule: Deep (deep) ---"));
+}
+
+#[test]
+fn folder_variables_reach_nested_rules() {
+    let s = common::sandbox();
+    common::set_list(vec![json!({
+        "id": "f", "name": "F", "type": "folder", "vars": { "IP": "10.0.0.9" },
+        "children": [{ "id": "r", "name": "R", "type": "file", "on": true }]
+    })]);
+    s.write("hosts_content_r.txt", "${IP} api.test\n");
+    let content = commands::get_content_of_list(None).unwrap();
+    assert!(content.contains("10.0.0.9 api.test"), "{}", content);
+}
+
+#[test]
+fn missing_content_files_compose_as_empty() {
+    let s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "gone", "name": "Gone", "type": "file", "on": true }),
+        json!({ "id": "here", "name": "Here", "type": "file", "on": true }),
+    ]);
+    s.write("hosts_content_here.txt", "1.2.3.4 here.test\n");
+
+    assert_eq!(commands::get_hosts_content("gone".into()), "");
+    let content = commands::get_content_of_list(None).unwrap();
+    assert!(content.contains("here.test"));
+}
+
+#[test]
+fn corrupt_json_reads_as_defaults() {
+    let s = common::sandbox();
+    s.write("list.json", "[{ not json");
+    s.write("variables.json", "{{");
+    assert!(commands::get_list().is_empty());
+    assert!(commands::get_variables().is_empty());
+    assert_eq!(commands::get_content_of_list(None).unwrap(), "");
+    // reading must not clobber the file the user may still want to repair
+    assert_eq!(
+        fs::read_to_string(s.data_dir().join("list.json")).unwrap(),
+        "[{ not json"
+    );
+}
+
+#[test]
+fn apply_writes_managed_block_to_sandbox() {
+    let s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "a", "name": "A", "type": "file", "on": true }),
+    ]);
+    s.write("hosts_content_a.txt", "1.2.3.4 a.test\n");
+
+    let res = commands::apply_system_hosts(None);
+    assert_eq!(res["success"], true, "{}", res);
+    let hosts = s.read_hosts();
+    assert!(hosts.starts_with(common::SYSTEM_HOSTS.trim_end()));
+    assert!(hosts.contains("# --- HOSTSMANAGER_CONTENT_START ---"));
+    assert!(hosts.contains("1.2.3.4 a.test"));
+    assert_eq!(commands::get_system_hosts(), hosts);
+
+    // applying again replaces the block instead of appending a second one
+    commands::apply_system_hosts(None);
+    assert_eq!(s.read_hosts().matches("CONTENT_START").count(), 1);
+}
+
+#[test]
+fn history_and_rollback_use_the_sandbox() {
+    let s = common::sandbox();
+    commands::set_system_hosts("1.1.1.1 one.test\n".into(), None);
+    commands::set_system_hosts("2.2.2.2 two.test\n".into(), None);
+
+    let history = commands::get_history();
+    assert_eq!(history.len(), 2);
+    let res = commands::rollback_history(history[0].id.clone(), None);
+    assert_eq!(res["success"], true);
+    assert_eq!(s.read_hosts(), "1.1.1.1 one.test\n");
+
+    let res = commands::rollback_history("nope".into(), None);
+    assert_eq!(res["code"], "not_found");
+}
+
+#[test]
+fn failed_system_write_is_reported_and_audited() {
+    let s = common::sandbox();
+    // a directory where the hosts file should be makes every write fail
+    fs::remove_file(s.hosts_path()).unwrap();
+    fs::create_dir(s.hosts_path()).unwrap();
+
+    let res = commands::set_system_hosts("1.1.1.1 x.test\n".into(), None);
+    assert_eq!(res["success"], false);
+    assert_eq!(res["code"], "write_failed");
+    assert!(commands::get_history().is_empty());
+
+    let log = commands::get_audit_log(None);
+    assert_eq!(log.len(), 1);
+    assert!(!log[0].success);
+    assert_eq!(log[0].action, audit::Action::Write);
+}
+
+#[test]
+fn failed_data_writes_return_false() {
+    let s = common::sandbox();
+    fs::create_dir_all(s.data_dir().join("list.json")).unwrap();
+    assert!(commands::set_list(vec![json!({ "id": "a" })], None).is_err());
+
+    fs::create_dir_all(s.data_dir().join("hosts_content_a.txt")).unwrap();
+    assert!(!commands::set_hosts_content("a".into(), "x".into()).unwrap());
+}
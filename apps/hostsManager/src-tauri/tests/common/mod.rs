@@ -0,0 +1,79 @@
+// Shared fixture for the command tests.
+//
+// Commands find their files through environment variables, so every test takes
+// a process-wide lock and points `HOSTSMANAGER_DATA_DIR`,
+// `HOSTSMANAGER_CONFIG_DIR` and the safe-mode hosts file at a fresh temporary
+// directory. The lock is held until the returned `Sandbox` is dropped.
+#![allow(dead_code)]
+
+use hostsmanager_lib::commands;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+pub const SYSTEM_HOSTS: &str = "127.0.0.1 localhost\n";
+
+pub struct Sandbox {
+    pub root: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+pub fn sandbox() -> Sandbox {
+    // a failed test poisons the lock; the next one still gets a clean env
+    let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().expect("temp dir");
+    let sandbox = Sandbox { root, _lock: lock };
+    env::set_var("HOSTSMANAGER_DATA_DIR", sandbox.data_dir());
+    env::set_var(
+        "HOSTSMANAGER_CONFIG_DIR",
+        sandbox.root.path().join("config"),
+    );
+    env::set_var("HOSTSMANAGER_SAFE_MODE", "1");
+    env::set_var("HOSTSMANAGER_SAFE_HOSTS_PATH", sandbox.hosts_path());
+    env::remove_var("SWEETHOSTS_SAFE_MODE");
+    fs::write(sandbox.hosts_path(), SYSTEM_HOSTS).expect("seed hosts");
+    sandbox
+}
+
+/// Saves `list` unconditionally.
+pub fn set_list(list: Vec<Value>) {
+    commands::set_list(list, None).expect("set_list");
+}
+
+impl Sandbox {
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.path().join("data")
+    }
+
+    pub fn hosts_path(&self) -> PathBuf {
+        self.root.path().join("hosts")
+    }
+
+    pub fn read_hosts(&self) -> String {
+        fs::read_to_string(self.hosts_path()).unwrap_or_default()
+    }
+
+    /// Writes a file relative to the data directory.
+    pub fn write(&self, name: &str, content: &str) {
+        fs::create_dir_all(self.data_dir()).expect("data dir");
+        fs::write(self.data_dir().join(name), content).expect("write fixture");
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        for k in [
+            "HOSTSMANAGER_DATA_DIR",
+            "HOSTSMANAGER_CONFIG_DIR",
+            "HOSTSMANAGER_SAFE_MODE",
+            "HOSTSMANAGER_SAFE_HOSTS_PATH",
+        ] {
+            env::remove_var(k);
+        }
+    }
+}
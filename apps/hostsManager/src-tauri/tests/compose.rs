@@ -0,0 +1,179 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::compose::ComposeOptions;
+use serde_json::json;
+
+fn rules(s: &common::Sandbox, rules: &[(&str, i64, &str)]) {
+    common::set_list(
+        rules
+            .iter()
+            .map(|(id, priority, _)| {
+                json!({ "id": id, "name": id.to_uppercase(), "on": true, "priority": priority })
+            })
+            .collect(),
+    );
+    for (id, _, content) in rules {
+        s.write(&format!("hosts_content_{}.txt", id), content);
+    }
+}
+
+fn composed() -> Vec<String> {
+    commands::get_content_of_list(None)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn higher_priority_wins_and_ties_go_to_the_first_rule() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[
+            ("a", 0, "10.0.0.1 api.test\n10.0.0.1 web.test\n"),
+            ("b", 5, "10.0.0.2 api.test\n"),
+            ("c", 0, "10.0.0.3 web.test\n"),
+        ],
+    );
+    let lines = composed();
+    assert!(lines.contains(&"# overridden by B (b): 10.0.0.1 api.test".to_string()));
+    assert!(lines.contains(&"10.0.0.2 api.test".to_string()));
+    assert!(lines.contains(&"10.0.0.1 web.test".to_string()));
+    assert!(lines.contains(&"# overridden by A (a): 10.0.0.3 web.test".to_string()));
+}
+
+#[test]
+fn other_families_do_not_conflict() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[("a", 0, "10.0.0.1 api.test\n"), ("b", 0, "::1 api.test\n")],
+    );
+    let lines = composed();
+    assert!(lines.contains(&"10.0.0.1 api.test".to_string()));
+    assert!(lines.contains(&"::1 api.test".to_string()));
+}
+
+#[test]
+fn exact_duplicates_are_dropped() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[
+            ("a", 0, "10.0.0.1 api.test\n"),
+            (
+                "b",
+                0,
+                "10.0.0.1 API.test\n10.0.0.1 api.test www.test # both\n",
+            ),
+        ],
+    );
+    let content = commands::get_content_of_list(None).unwrap();
+    assert_eq!(content.matches("api.test").count(), 1, "{}", content);
+    assert!(content.contains("10.0.0.1 www.test # both"));
+}
+
+#[test]
+fn partly_overridden_lines_keep_their_comment_and_name_the_winner() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[
+            ("a", 0, "10.0.0.1 api.test www.test cdn.test # staging\n"),
+            ("b", 1, "10.0.0.2 api.test\n"),
+            ("c", 2, "10.0.0.3 cdn.test\n"),
+        ],
+    );
+    let lines = composed();
+    let at = lines
+        .iter()
+        .position(|l| l == "10.0.0.1 www.test # staging")
+        .unwrap();
+    assert_eq!(lines[at + 1], "# overridden by B (b): 10.0.0.1 api.test");
+    assert_eq!(lines[at + 2], "# overridden by C (c): 10.0.0.1 cdn.test");
+
+    // nothing left: the comment moves to the first note
+    rules(
+        &s,
+        &[
+            ("a", 0, "10.0.0.1 api.test cdn.test # staging\n"),
+            ("b", 1, "10.0.0.2 api.test\n"),
+            ("c", 2, "10.0.0.3 cdn.test\n"),
+        ],
+    );
+    let lines = composed();
+    assert!(lines.contains(&"# overridden by B (b): 10.0.0.1 api.test # staging".to_string()));
+    assert!(lines.contains(&"# overridden by C (c): 10.0.0.1 cdn.test".to_string()));
+}
+
+#[test]
+fn every_line_maps_back_to_its_rule() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[
+            ("a", 0, "10.0.0.1 api.test www.test\n"),
+            ("b", 1, "# b\n10.0.0.2 api.test\n"),
+        ],
+    );
+    let composition = commands::get_composition(None).unwrap();
+    let lines: Vec<&str> = composition.content.lines().collect();
+    for entry in &composition.source_map {
+        let text = lines[entry.line - 1];
+        match entry.rule_line {
+            None => assert!(text.starts_with("# --- rule:")),
+            Some(_) if text.contains("10.0.0.2") => assert_eq!(entry.rule_id, "b"),
+            Some(n) if entry.rule_id == "a" => assert_eq!(n, 1),
+            _ => {}
+        }
+    }
+    // blank separators between blocks are not mapped
+    assert_eq!(composition.source_map.len(), lines.len() - 1);
+}
+
+#[test]
+fn ipv6_companions_cover_local_addresses_only() {
+    let s = common::sandbox();
+    rules(
+        &s,
+        &[(
+            "a",
+            0,
+            "127.0.0.1 app.test\n0.0.0.0 ads.test both.test\n192.168.1.10 nas.lan\n::1 both.test\n",
+        )],
+    );
+    let opts = ComposeOptions {
+        ipv6_companions: true,
+        ..Default::default()
+    };
+    let content = commands::get_content_of_list(Some(opts)).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(lines.contains(&"::1 app.test"), "{}", content);
+    // both.test has an IPv6 mapping of its own
+    assert!(lines.contains(&":: ads.test"), "{}", content);
+    assert!(!content.contains(":: both.test"), "{}", content);
+    // nor does a LAN address get one
+    assert_eq!(content.matches("nas.lan").count(), 1);
+    // off by default
+    assert!(!commands::get_content_of_list(None)
+        .unwrap()
+        .contains("::1 app.test"));
+}
+
+#[test]
+fn ipv6_companions_setting_reaches_the_hosts_file() {
+    let s = common::sandbox();
+    rules(&s, &[("a", 0, "0.0.0.0 ads.test\n")]);
+    assert_eq!(commands::apply_system_hosts(None)["success"], true);
+    assert!(!s.read_hosts().contains(":: ads.test"));
+
+    let mut settings = commands::get_settings().stored;
+    settings.ipv6_companions = true;
+    commands::set_settings(settings).unwrap();
+    assert_eq!(commands::apply_system_hosts(None)["success"], true);
+    let hosts = s.read_hosts();
+    assert!(hosts.contains("0.0.0.0 ads.test"), "{}", hosts);
+    assert!(hosts.contains(":: ads.test"), "{}", hosts);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::store::{self, StoreError};
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 8;
+const WRITES: usize = 25;
+
+#[test]
+fn stale_revision_is_rejected() {
+    let _s = common::sandbox();
+    let stale = commands::get_list_revision();
+    let fresh = commands::set_list(vec![json!({ "id": "a" })], Some(stale.clone())).unwrap();
+    assert_eq!(fresh, commands::get_list_revision());
+
+    match commands::set_list(vec![json!({ "id": "b" })], Some(stale.clone())) {
+        Err(StoreError::Conflict { expected, found }) => {
+            assert_eq!(expected, stale);
+            assert_eq!(found, fresh);
+        }
+        other => panic!("expected a conflict, got {:?}", other),
+    }
+    assert_eq!(commands::get_list(), vec![json!({ "id": "a" })]);
+}
+
+#[test]
+fn conflict_serializes_with_code_and_revision() {
+    let err = StoreError::Conflict {
+        expected: "old".into(),
+        found: "new".into(),
+    };
+    let v = serde_json::to_value(&err).unwrap();
+    assert_eq!(v["code"], "conflict");
+    assert_eq!(v["revision"], "new");
+    assert!(v["message"].as_str().unwrap().contains("changed elsewhere"));
+}
+
+// Every thread appends its own items with read-modify-write plus retry on
+// conflict; with working revision checks no append is lost.
+#[test]
+fn concurrent_appends_lose_no_updates() {
+    let _s = common::sandbox();
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            thread::spawn(move || {
+                for n in 0..WRITES {
+                    loop {
+                        let mut snap = commands::get_list_with_revision();
+                        snap.list.push(json!({ "id": format!("{}-{}", t, n) }));
+                        match commands::set_list(snap.list, Some(snap.revision)) {
+                            Ok(_) => break,
+                            Err(StoreError::Conflict { .. }) => continue,
+                            Err(e) => panic!("{}", e),
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    let mut ids: Vec<String> = commands::get_list()
+        .iter()
+        .map(|v| v["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), THREADS * WRITES);
+}
+
+// Readers racing with writers only ever see one complete version.
+#[test]
+fn content_reads_never_see_partial_writes() {
+    let _s = common::sandbox();
+    let versions: Vec<String> = (0..THREADS)
+        .map(|t| format!("10.0.0.{} host{}.test\n", t, t).repeat(2000))
+        .collect();
+    assert!(commands::set_hosts_content("r".into(), versions[0].clone()).unwrap());
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let done = done.clone();
+        let versions = versions.clone();
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                let c = commands::get_hosts_content("r".into());
+                assert!(versions.contains(&c), "torn read of {} bytes", c.len());
+            }
+        })
+    };
+    let writers: Vec<_> = versions
+        .iter()
+        .cloned()
+        .map(|v| {
+            thread::spawn(move || {
+                for _ in 0..WRITES {
+                    assert!(commands::set_hosts_content("r".into(), v.clone()).unwrap());
+                }
+            })
+        })
+        .collect();
+    for w in writers {
+        w.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    reader.join().unwrap();
+}
+
+// Another process holding the lock file blocks writers until it lets go. A
+// second open file description behaves like another process for `flock`.
+#[test]
+fn writers_wait_for_the_lock_file() {
+    let s = common::sandbox();
+    drop(store::lock().unwrap()); // creates the lock file
+    let other = OpenOptions::new()
+        .write(true)
+        .open(s.data_dir().join(".lock"))
+        .unwrap();
+    other.lock().unwrap();
+
+    let written = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let written = written.clone();
+        thread::spawn(move || {
+            common::set_list(vec![json!({ "id": "late" })]);
+            written.store(true, Ordering::SeqCst);
+        })
+    };
+    thread::sleep(Duration::from_millis(200));
+    assert!(!written.load(Ordering::SeqCst));
+
+    other.unlock().unwrap();
+    writer.join().unwrap();
+    assert_eq!(commands::get_list(), vec![json!({ "id": "late" })]);
+}
@@ -0,0 +1,89 @@
+use hostsmanager_lib::deeplink::{self, Link, LinkError};
+use serde_json::json;
+
+#[test]
+fn parses_scheme_links() {
+    assert_eq!(deeplink::parse("hostsmanager://open"), Ok(Link::Open));
+    assert_eq!(
+        deeplink::parse("hostsmanager://openRuleById?id=abc"),
+        Ok(Link::OpenRuleById { id: "abc".into() })
+    );
+    assert_eq!(deeplink::parse("hostsmanager://apply/"), Ok(Link::Apply));
+}
+
+// what the launcher builds: the action and its arguments encoded in `args`
+#[test]
+fn parses_launcher_links() {
+    assert_eq!(
+        deeplink::parse("hostsmanager://open?args=action%3DopenRuleByName%26name%3DDev%2520box"),
+        Ok(Link::OpenRuleByName {
+            name: "Dev box".into()
+        })
+    );
+    assert_eq!(
+        deeplink::parse("tlfsuite://open?app=hostsManager&args=action%3DenableRule%26id%3Dx"),
+        Ok(Link::EnableRule { id: "x".into() })
+    );
+    assert_eq!(
+        deeplink::parse("tlfsuite://open?app=hostsmanager"),
+        Ok(Link::Open)
+    );
+}
+
+#[test]
+fn rejects_links_that_do_not_match_the_manifest() {
+    assert!(matches!(
+        deeplink::parse("tlfsuite://open?app=launcher"),
+        Err(LinkError::Foreign(_))
+    ));
+    assert!(matches!(
+        deeplink::parse("https://example.com/open"),
+        Err(LinkError::Foreign(_))
+    ));
+    assert!(matches!(
+        deeplink::parse("not a url"),
+        Err(LinkError::Malformed(_))
+    ));
+    assert_eq!(
+        deeplink::parse("hostsmanager://applyProfile?name=work"),
+        Err(LinkError::UnknownAction("applyProfile".into()))
+    );
+    assert_eq!(
+        deeplink::parse("hostsmanager://disableRule?id=%20"),
+        Err(LinkError::MissingArg {
+            action: "disableRule".into(),
+            arg: "id".into()
+        })
+    );
+    assert_eq!(
+        deeplink::parse("hostsmanager://open?id=x"),
+        Err(LinkError::UnexpectedArg {
+            action: "open".into(),
+            arg: "id".into()
+        })
+    );
+}
+
+#[test]
+fn links_serialize_with_their_action_name() {
+    let v = serde_json::to_value(Link::OpenRuleById { id: "a".into() }).unwrap();
+    assert_eq!(v, json!({ "action": "openRuleById", "id": "a" }));
+}
+
+#[test]
+fn picks_links_out_of_second_instance_args() {
+    let argv: Vec<String> = [
+        "hostsmanager",
+        "--flag",
+        "hostsmanager://apply",
+        "tlfsuite://open?app=other",
+        "hostsmanager://nope",
+    ]
+    .map(String::from)
+    .to_vec();
+    // invalid links for this app are kept so they can be reported
+    assert_eq!(
+        deeplink::links_in_args(&argv),
+        vec!["hostsmanager://apply", "hostsmanager://nope"]
+    );
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use serde_json::json;
+
+fn with_rules(s: &common::Sandbox) {
+    common::set_list(vec![
+        json!({ "id": "a", "type": "file", "on": true }),
+        json!({ "id": "b", "type": "file", "on": false }),
+    ]);
+    s.write(
+        "hosts_content_a.txt",
+        "# dev\n10.0.0.1 Api.Test www.api.test\n::1 api.test\n10.0.0.1 api.test\n1.2.3.4 bad_host! ok.test.\n",
+    );
+    s.write("hosts_content_b.txt", "9.9.9.9 off.test\n");
+}
+
+#[test]
+fn exports_dnsmasq_and_unbound() {
+    let s = common::sandbox();
+    with_rules(&s);
+    assert_eq!(
+        commands::export_as("dnsmasq".into()).unwrap(),
+        "address=/api.test/10.0.0.1\naddress=/www.api.test/10.0.0.1\n\
+         address=/api.test/::1\naddress=/ok.test/1.2.3.4\n"
+    );
+    assert_eq!(
+        commands::export_as("unbound".into()).unwrap(),
+        "server:\n    local-data: \"api.test. IN A 10.0.0.1\"\n    \
+         local-data: \"www.api.test. IN A 10.0.0.1\"\n    \
+         local-data: \"api.test. IN AAAA ::1\"\n    local-data: \"ok.test. IN A 1.2.3.4\"\n"
+    );
+}
+
+#[test]
+fn exports_coredns_and_json() {
+    let s = common::sandbox();
+    with_rules(&s);
+    let coredns = commands::export_as("coredns".into()).unwrap();
+    assert!(coredns.starts_with("hosts {\n    10.0.0.1 api.test\n"));
+    assert!(coredns.ends_with("    fallthrough\n}\n"));
+
+    let v: serde_json::Value =
+        serde_json::from_str(&commands::export_as("json".into()).unwrap()).unwrap();
+    assert_eq!(
+        v,
+        json!({
+            "api.test": ["10.0.0.1", "::1"],
+            "ok.test": ["1.2.3.4"],
+            "www.api.test": ["10.0.0.1"],
+        })
+    );
+}
+
+#[test]
+fn unknown_format_is_an_error() {
+    let _s = common::sandbox();
+    assert!(commands::export_as("bind".into()).is_err());
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use serde_json::{json, Map, Value};
+
+fn patch(v: Value) -> Map<String, Value> {
+    v.as_object().unwrap().clone()
+}
+
+// system rules cannot be added through the app, so the list is written as if
+// by hand
+fn with_flagged_rules(s: &common::Sandbox) {
+    s.write(
+        "list.json",
+        &json!([
+            { "id": "sys", "name": "System", "system": true, "on": true },
+            { "id": "lock", "name": "Locked", "locked": true, "on": true },
+            { "id": "pin", "name": "Pinned", "pinned": true, "on": true },
+            { "id": "f", "name": "Folder", "type": "folder", "children": [
+                { "id": "sys2", "name": "Nested", "system": true }
+            ] },
+        ])
+        .to_string(),
+    );
+    s.write("hosts_content_lock.txt", "10.0.0.1 locked.test\n");
+    s.write("hosts_content_pin.txt", "10.0.0.2 pinned.test\n");
+}
+
+#[test]
+fn system_rules_mirror_the_hosts_file_and_stay() {
+    let s = common::sandbox();
+    with_flagged_rules(&s);
+    assert_eq!(
+        commands::get_hosts_content("sys".into()),
+        common::SYSTEM_HOSTS
+    );
+    let err = commands::set_hosts_content("sys".into(), "x".into()).unwrap_err();
+    assert_eq!((err.code(), err.rule_id()), ("system", "sys"));
+
+    assert_eq!(
+        commands::delete_item("sys".into()).unwrap_err().code(),
+        "system"
+    );
+    // deleting the folder would take the nested system rule with it
+    assert_eq!(
+        commands::delete_item("f".into()).unwrap_err().code(),
+        "system"
+    );
+    assert_eq!(
+        commands::update_item("sys".into(), patch(json!({ "system": null })))
+            .unwrap_err()
+            .code(),
+        "system"
+    );
+    let mut list = commands::get_list();
+    list.retain(|item| item["id"] != "sys");
+    let err = commands::set_list(list, None).unwrap_err();
+    assert_eq!(serde_json::to_value(&err).unwrap()["rule_id"], "sys");
+    // nothing was written
+    assert_eq!(commands::get_list().len(), 4);
+    // an existing rule cannot become one either
+    assert_eq!(
+        commands::update_item("lock".into(), patch(json!({ "system": true })))
+            .unwrap_err()
+            .code(),
+        "system"
+    );
+}
+
+#[test]
+fn new_rules_never_mirror_the_hosts_file() {
+    let s = common::sandbox();
+    with_flagged_rules(&s);
+    let created =
+        commands::create_item(None, json!({ "name": "New", "system": true }), None, None).unwrap();
+    assert!(created.get("system").is_none());
+    let copy = commands::duplicate_item("sys".into()).unwrap();
+    assert!(copy.get("system").is_none());
+    let copy = commands::duplicate_item("f".into()).unwrap();
+    assert!(copy["children"][0].get("system").is_none());
+
+    let mut list = commands::get_list();
+    list.push(json!({ "id": "sys3", "name": "Another", "system": true }));
+    let err = commands::set_list(list, None).unwrap_err();
+    assert_eq!(serde_json::to_value(&err).unwrap()["rule_id"], "sys3");
+}
+
+#[test]
+fn locked_content_is_read_only_until_unlocked() {
+    let s = common::sandbox();
+    with_flagged_rules(&s);
+    let err = commands::set_hosts_content("lock".into(), "x".into()).unwrap_err();
+    assert_eq!(err.code(), "locked");
+    assert!(commands::import_blocklist("lock".into(), "ads.test\n".into(), None).is_err());
+    assert_eq!(
+        commands::get_hosts_content("lock".into()),
+        "10.0.0.1 locked.test\n"
+    );
+    // the rule itself can still be renamed, and unlocked
+    commands::update_item("lock".into(), patch(json!({ "name": "Renamed" }))).unwrap();
+    commands::update_item("lock".into(), patch(json!({ "locked": false }))).unwrap();
+    assert!(commands::set_hosts_content("lock".into(), "x".into()).unwrap());
+}
+
+#[test]
+fn pinned_rules_cannot_be_switched_off() {
+    let s = common::sandbox();
+    with_flagged_rules(&s);
+    assert_eq!(
+        commands::update_item("pin".into(), patch(json!({ "on": false })))
+            .unwrap_err()
+            .code(),
+        "pinned"
+    );
+    let mut list = commands::get_list();
+    list[2]["on"] = json!(false);
+    assert_eq!(
+        serde_json::to_value(commands::set_list(list, None).unwrap_err()).unwrap()["code"],
+        "pinned"
+    );
+    let created =
+        commands::create_item(None, json!({ "name": "New", "pinned": true }), None, None).unwrap();
+    assert_eq!(created["on"], true);
+}
+
+// pinned rules are composed even when stored switched off; system rules never
+#[test]
+fn composition_follows_the_flags() {
+    let s = common::sandbox();
+    s.write("hosts_content_sys.txt", "10.0.0.9 stale.test\n");
+    s.write("hosts_content_pin.txt", "10.0.0.2 pinned.test\n");
+    // as if edited by hand
+    s.write(
+        "list.json",
+        &json!([
+            { "id": "sys", "name": "System", "system": true, "on": true },
+            { "id": "pin", "name": "Pinned", "pinned": true, "on": false },
+        ])
+        .to_string(),
+    );
+    let content = commands::get_content_of_list(None).unwrap();
+    assert!(content.contains("10.0.0.2 pinned.test"));
+    assert!(!content.contains("stale.test"));
+    // a violation already stored does not block unrelated edits
+    commands::update_item("sys".into(), patch(json!({ "name": "Hosts" }))).unwrap();
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::compose::MAX_INCLUDE_DEPTH;
+use serde_json::{json, Value};
+
+fn rule(id: &str, on: bool) -> Value {
+    json!({ "id": id, "name": id.to_uppercase(), "on": on })
+}
+
+#[test]
+fn includes_pull_in_other_rules_enabled_or_not() {
+    let s = common::sandbox();
+    common::set_list(vec![rule("main", true), rule("shared", false)]);
+    s.write(
+        "hosts_content_main.txt",
+        "10.0.0.1 main.test\n  @include   shared\n10.0.0.3 after.test\n",
+    );
+    s.write("hosts_content_shared.txt", "10.0.0.2 shared.test\n");
+    let content = commands::get_content_of_list(None).unwrap();
+    let main = content.find("main.test").unwrap();
+    let shared = content.find("shared.test").unwrap();
+    let after = content.find("after.test").unwrap();
+    assert!(main < shared && shared < after, "{}", content);
+    assert!(!content.contains("@include"));
+    // the disabled rule gets no block of its own
+    assert!(!content.contains("rule: SHARED"));
+}
+
+#[test]
+fn cycles_name_the_rule_and_line() {
+    let s = common::sandbox();
+    common::set_list(vec![rule("a", true), rule("b", false), rule("c", false)]);
+    s.write("hosts_content_a.txt", "@include b\n");
+    s.write("hosts_content_b.txt", "# b\n@include c\n");
+    s.write("hosts_content_c.txt", "10.0.0.1 c.test\n\n@include a\n");
+    let err = commands::get_content_of_list(None).unwrap_err();
+    assert_eq!(err, "C (c), line 3: include cycle: a -> b -> c -> a");
+
+    // including itself is the shortest cycle
+    s.write("hosts_content_a.txt", "@include a\n");
+    let err = commands::get_content_of_list(None).unwrap_err();
+    assert!(err.starts_with("A (a), line 1: include cycle"), "{}", err);
+}
+
+#[test]
+fn unknown_ids_and_missing_arguments_fail() {
+    let s = common::sandbox();
+    common::set_list(vec![rule("a", true)]);
+    s.write("hosts_content_a.txt", "@include nope\n");
+    let err = commands::get_content_of_list(None).unwrap_err();
+    assert_eq!(err, "A (a), line 1: unknown rule id `nope`");
+    s.write("hosts_content_a.txt", "\n@include\n");
+    let err = commands::get_content_of_list(None).unwrap_err();
+    assert_eq!(err, "A (a), line 2: `@include` needs a rule id");
+    // only the directive itself counts
+    s.write("hosts_content_a.txt", "@includes a\n# @include a\n");
+    assert!(commands::get_content_of_list(None).is_ok());
+}
+
+// r0 includes r1, which includes r2, and so on down to r{levels}
+fn chain(s: &common::Sandbox, levels: usize) {
+    let ids: Vec<String> = (0..=levels).map(|i| format!("r{}", i)).collect();
+    common::set_list(
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| rule(id, i == 0))
+            .collect(),
+    );
+    for (i, id) in ids.iter().enumerate() {
+        let content = match ids.get(i + 1) {
+            Some(next) => format!("@include {}\n", next),
+            None => "10.0.0.1 deep.test\n".to_string(),
+        };
+        s.write(&format!("hosts_content_{}.txt", id), &content);
+    }
+}
+
+#[test]
+fn nesting_is_limited() {
+    let s = common::sandbox();
+    chain(&s, MAX_INCLUDE_DEPTH);
+    assert!(commands::get_content_of_list(None)
+        .unwrap()
+        .contains("10.0.0.1 deep.test"));
+
+    chain(&s, MAX_INCLUDE_DEPTH + 1);
+    let err = commands::get_content_of_list(None).unwrap_err();
+    let last = format!("r{}", MAX_INCLUDE_DEPTH);
+    assert!(
+        err.starts_with(&format!(
+            "{} ({}), line 1: includes are nested deeper",
+            last.to_uppercase(),
+            last
+        )),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::items::{self, ItemError};
+use serde_json::{json, Map, Value};
+
+fn ids(items: &[Value]) -> Vec<&str> {
+    items.iter().map(|v| v["id"].as_str().unwrap()).collect()
+}
+
+fn patch(v: Value) -> Map<String, Value> {
+    v.as_object().unwrap().clone()
+}
+
+#[test]
+fn create_assigns_id_time_and_content() {
+    let _s = common::sandbox();
+    let folder =
+        commands::create_item(None, json!({ "name": "F", "type": "folder" }), None, None).unwrap();
+    let fid = folder["id"].as_str().unwrap().to_string();
+    assert_eq!(folder["children"], json!([]));
+
+    let rule = commands::create_item(
+        Some(fid.clone()),
+        json!({ "id": "ignored", "name": "R", "on": true }),
+        None,
+        Some("1.2.3.4 r.test\n".into()),
+    )
+    .unwrap();
+    let rid = rule["id"].as_str().unwrap().to_string();
+    assert_ne!(rid, "ignored");
+    assert_eq!(rule["type"], "file");
+    assert!(rule["add_time_ms"].as_u64().unwrap() > 0);
+    assert_eq!(commands::get_hosts_content(rid.clone()), "1.2.3.4 r.test\n");
+
+    let list = commands::get_list();
+    assert_eq!(
+        ids(list[0]["children"].as_array().unwrap()),
+        vec![rid.as_str()]
+    );
+
+    let err = commands::create_item(Some(rid), json!({}), None, None).unwrap_err();
+    assert!(matches!(err, ItemError::NotAFolder(_)));
+}
+
+#[test]
+fn update_merges_and_guards_structure() {
+    let _s = common::sandbox();
+    let rule =
+        commands::create_item(None, json!({ "name": "R", "on": false }), None, None).unwrap();
+    let id = rule["id"].as_str().unwrap().to_string();
+
+    let updated =
+        commands::update_item(id.clone(), patch(json!({ "on": true, "name": null }))).unwrap();
+    assert_eq!(updated["on"], true);
+    assert!(updated.get("name").is_none());
+    assert_eq!(commands::get_list()[0], updated);
+
+    let err = commands::update_item(id.clone(), patch(json!({ "id": "x" }))).unwrap_err();
+    assert_eq!(err.code(), "invalid");
+    let err = commands::update_item("missing".into(), patch(json!({ "on": true }))).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
+
+#[test]
+fn move_reorders_and_refuses_cycles() {
+    let _s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "a", "type": "file" }),
+        json!({ "id": "b", "type": "file" }),
+        json!({ "id": "f", "type": "folder", "children": [
+            { "id": "g", "type": "folder", "children": [] }
+        ] }),
+    ]);
+
+    commands::move_item("b".into(), None, Some(0)).unwrap();
+    assert_eq!(ids(&commands::get_list()), vec!["b", "a", "f"]);
+
+    commands::move_item("a".into(), Some("g".into()), None).unwrap();
+    let list = commands::get_list();
+    assert_eq!(list[1]["children"][0]["children"][0]["id"], "a");
+
+    let err = commands::move_item("f".into(), Some("g".into()), None).unwrap_err();
+    assert!(matches!(err, ItemError::IntoItself(_)));
+    let err = commands::move_item("b".into(), Some("nope".into()), None).unwrap_err();
+    assert!(matches!(err, ItemError::NotFound(_)));
+    // failed moves leave the tree alone
+    assert_eq!(commands::get_list(), list);
+}
+
+#[test]
+fn delete_removes_subtree_and_content() {
+    let s = common::sandbox();
+    common::set_list(vec![json!({ "id": "f", "type": "folder", "children": [
+        { "id": "a", "type": "file" }
+    ] })]);
+    s.write("hosts_content_a.txt", "1.1.1.1 a.test\n");
+
+    let removed = commands::delete_item("f".into()).unwrap();
+    assert_eq!(removed, vec!["f", "a"]);
+    assert!(commands::get_list().is_empty());
+    assert!(!s.data_dir().join("hosts_content_a.txt").exists());
+}
+
+#[test]
+fn duplicate_copies_subtree_with_content() {
+    let s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "f", "name": "F", "type": "folder", "children": [
+            { "id": "a", "name": "A", "type": "file", "on": true }
+        ] }),
+        json!({ "id": "z", "type": "file" }),
+    ]);
+    s.write("hosts_content_a.txt", "1.1.1.1 a.test\n");
+
+    let copy = commands::duplicate_item("f".into()).unwrap();
+    let list = commands::get_list();
+    assert_eq!(ids(&list), vec!["f", copy["id"].as_str().unwrap(), "z"]);
+
+    let child = &copy["children"][0];
+    assert_ne!(child["id"], "a");
+    assert_eq!(child["name"], "A");
+    assert_eq!(
+        commands::get_hosts_content(child["id"].as_str().unwrap().into()),
+        "1.1.1.1 a.test\n"
+    );
+}
+
+#[test]
+fn corrupt_list_is_not_replaced() {
+    let s = common::sandbox();
+    s.write("list.json", "[{ not json");
+    let err = commands::create_item(None, json!({}), None, None).unwrap_err();
+    assert_eq!(err.code(), "io");
+    assert_eq!(
+        std::fs::read_to_string(s.data_dir().join("list.json")).unwrap(),
+        "[{ not json"
+    );
+}
+
+#[test]
+fn list_writes_notify_listeners() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let _s = common::sandbox();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    hostsmanager_lib::store::on_list_changed(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let before = calls.load(Ordering::SeqCst);
+    common::set_list(vec![]);
+    let item = commands::create_item(None, json!({}), None, None).unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), before + 2);
+
+    // failed edits leave listeners alone
+    commands::update_item("missing".into(), patch(json!({ "on": true }))).unwrap_err();
+    assert_eq!(calls.load(Ordering::SeqCst), before + 2);
+    commands::delete_item(item["id"].as_str().unwrap().into()).unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), before + 3);
+}
+
+#[test]
+fn activating_a_profile_switches_the_top_level_folders() {
+    let _s = common::sandbox();
+    common::set_list(vec![
+        json!({ "id": "base", "name": "Base", "on": true }),
+        json!({ "id": "dev", "name": "Dev", "type": "folder", "children": [
+            { "id": "d1", "name": "D1", "on": false },
+            { "id": "sub", "name": "Sub", "type": "folder", "children": [
+                { "id": "d2", "name": "D2", "on": false }
+            ] }
+        ] }),
+        json!({ "id": "staging", "name": "Staging", "type": "folder", "children": [
+            { "id": "s1", "name": "S1", "on": true },
+            { "id": "s2", "name": "S2", "on": true, "pinned": true }
+        ] }),
+    ]);
+    assert_eq!(
+        items::active_profile(&commands::get_list()),
+        Some("staging")
+    );
+
+    assert!(items::activate_profile("dev").unwrap());
+    let list = commands::get_list();
+    let on = |id: &str| hostsmanager_lib::tree::find(&list, id).unwrap()["on"] == true;
+    assert!(on("d1") && on("d2") && on("base") && on("s2"));
+    assert!(!on("s1"));
+    assert_eq!(items::active_profile(&list), Some("dev"));
+    assert!(!items::activate_profile("dev").unwrap());
+
+    // a rule switched on by hand in another profile mixes them
+    commands::update_item("s1".into(), patch(json!({ "on": true }))).unwrap();
+    assert_eq!(items::active_profile(&commands::get_list()), None);
+
+    assert!(matches!(
+        items::activate_profile("base"),
+        Err(ItemError::NotAFolder(_))
+    ));
+    assert!(matches!(
+        items::activate_profile("sub"),
+        Err(ItemError::NotFound(_))
+    ));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use hostsmanager_lib::migrations::{self, MigrationError, CURRENT_VERSION};
+use serde_json::{json, Value};
+use std::fs;
+
+fn read_json(s: &common::Sandbox, name: &str) -> Value {
+    serde_json::from_str(&fs::read_to_string(s.data_dir().join(name)).unwrap()).unwrap()
+}
+
+#[test]
+fn fresh_directories_start_at_the_current_version() {
+    let s = common::sandbox();
+    fs::create_dir_all(s.data_dir()).unwrap();
+    assert_eq!(migrations::run(&s.data_dir()).unwrap(), CURRENT_VERSION);
+    assert_eq!(
+        migrations::read_version(&s.data_dir()).unwrap(),
+        Some(CURRENT_VERSION)
+    );
+    assert!(!s.data_dir().join("backups").exists());
+}
+
+#[test]
+fn v0_lists_are_upgraded_after_a_backup() {
+    let s = common::sandbox();
+    let legacy = json!([
+        { "id": "a", "title": "Old title", "groupId": "g", "color": "red", "on": true },
+        { "id": "f", "name": "Folder", "children": [
+            { "id": "b", "name": "Kept", "title": "ignored", "type": "remote" }
+        ] },
+    ]);
+    s.write("list.json", &legacy.to_string());
+    s.write("hosts_content_a.txt", "10.0.0.1 a.test\n");
+    assert_eq!(migrations::read_version(&s.data_dir()).unwrap(), Some(0));
+
+    assert_eq!(migrations::run(&s.data_dir()).unwrap(), 0);
+    let list = read_json(&s, "list.json");
+    assert_eq!(list[0]["name"], "Old title");
+    assert_eq!(list[0]["type"], "file");
+    assert!(list[0].get("groupId").is_none() && list[0].get("color").is_none());
+    assert_eq!(list[1]["type"], "folder");
+    assert_eq!(list[1]["children"][0]["name"], "Kept");
+    assert_eq!(list[1]["children"][0]["type"], "remote");
+    assert_eq!(read_json(&s, "schema.json")["version"], CURRENT_VERSION);
+
+    // the backup holds the data as it was
+    let backups: Vec<_> = fs::read_dir(s.data_dir().join("backups"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0]
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("pre-migration-v0-"));
+    let saved: Value =
+        serde_json::from_str(&fs::read_to_string(backups[0].join("list.json")).unwrap()).unwrap();
+    assert_eq!(saved, legacy);
+    assert!(backups[0].join("hosts_content_a.txt").exists());
+
+    // a second run has nothing to do
+    assert_eq!(migrations::run(&s.data_dir()).unwrap(), CURRENT_VERSION);
+    assert_eq!(
+        fs::read_dir(s.data_dir().join("backups")).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn newer_versions_are_refused_untouched() {
+    let s = common::sandbox();
+    let list = r#"[{"id":"a","title":"T"}]"#;
+    s.write("list.json", list);
+    s.write(
+        "schema.json",
+        &json!({ "version": CURRENT_VERSION + 1 }).to_string(),
+    );
+    match migrations::run(&s.data_dir()) {
+        Err(MigrationError::NewerVersion {
+            found, supported, ..
+        }) => assert_eq!((found, supported), (CURRENT_VERSION + 1, CURRENT_VERSION)),
+        other => panic!("expected NewerVersion, got {:?}", other),
+    }
+    assert_eq!(
+        fs::read_to_string(s.data_dir().join("list.json")).unwrap(),
+        list
+    );
+    assert!(!s.data_dir().join("backups").exists());
+
+    s.write("schema.json", "{}");
+    assert!(matches!(
+        migrations::run(&s.data_dir()),
+        Err(MigrationError::Corrupt { .. })
+    ));
+}
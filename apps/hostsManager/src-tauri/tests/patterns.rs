@@ -0,0 +1,88 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::compose::ComposeOptions;
+use hostsmanager_lib::resolver::Table;
+use serde_json::json;
+use std::net::IpAddr;
+
+fn with_pattern_hosts(names: &[&str]) {
+    let mut settings = commands::get_settings().stored;
+    settings.pattern_hosts = names.iter().map(|n| n.to_string()).collect();
+    commands::set_settings(settings).unwrap();
+}
+
+fn entries(content: &str) -> Vec<&str> {
+    content
+        .lines()
+        .filter(|l| !l.starts_with("# --- rule"))
+        .collect()
+}
+
+#[test]
+fn patterns_expand_against_pattern_hosts() {
+    let s = common::sandbox();
+    with_pattern_hosts(&[
+        "a.preview.internal",
+        "preview.internal",
+        "api-7.test",
+        "B.Preview.Internal",
+    ]);
+    common::set_list(vec![json!({ "id": "r", "on": true })]);
+    s.write(
+        "hosts_content_r.txt",
+        "10.0.0.1 *.preview.internal keep.test\n10.0.0.2 /api-[0-9]+\\.test/\n10.0.0.3 *.nothing.test\n",
+    );
+
+    let content = commands::get_content_of_list(None).unwrap();
+    assert_eq!(
+        entries(&content),
+        vec![
+            "10.0.0.1 a.preview.internal B.Preview.Internal keep.test",
+            "10.0.0.2 api-7.test",
+            "# no pattern hosts match: 10.0.0.3 *.nothing.test",
+        ]
+    );
+
+    let kept = commands::get_content_of_list(Some(ComposeOptions {
+        keep_patterns: true,
+        ..Default::default()
+    }))
+    .unwrap();
+    assert!(kept.contains("10.0.0.1 *.preview.internal keep.test\n"));
+}
+
+// expanded names take part in conflict resolution like any other
+#[test]
+fn expanded_names_are_overridden_by_priority() {
+    let s = common::sandbox();
+    with_pattern_hosts(&["x.dev.test", "y.dev.test"]);
+    common::set_list(vec![
+        json!({ "id": "wild", "name": "Wild", "on": true }),
+        json!({ "id": "pin", "name": "Pin", "on": true, "priority": 5 }),
+    ]);
+    s.write("hosts_content_wild.txt", "10.0.0.1 *.dev.test\n");
+    s.write("hosts_content_pin.txt", "10.0.0.9 y.dev.test\n");
+    assert!(commands::apply_system_hosts(None)["success"] == true);
+    let hosts = s.read_hosts();
+    assert!(hosts.contains("10.0.0.1 x.dev.test\n"));
+    assert!(hosts.contains("10.0.0.9 y.dev.test\n"));
+    assert!(!hosts.contains('*'));
+}
+
+#[test]
+fn validator_flags_patterns() {
+    let warnings = commands::validate_hosts_content("10.0.0.1 *.a.test\n10.0.0.2 /api-(/\n".into());
+    let codes: Vec<(usize, &str)> = warnings.iter().map(|w| (w.line, w.code)).collect();
+    assert_eq!(codes, vec![(1, "pattern_host"), (2, "invalid_pattern")]);
+}
+
+#[test]
+fn resolver_table_serves_regexes_after_wildcards() {
+    let table =
+        Table::from_hosts("10.0.0.1 /.*\\.svc\\.test/\n10.0.0.2 *.web.svc.test\n10.0.0.3 /bad(/\n");
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+    assert_eq!(table.lookup("db.svc.test"), Some(&[ip("10.0.0.1")][..]));
+    assert_eq!(table.lookup("a.web.svc.test"), Some(&[ip("10.0.0.2")][..]));
+    assert_eq!(table.lookup("svc.test"), None);
+}
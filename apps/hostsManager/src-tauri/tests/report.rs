@@ -0,0 +1,77 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use serde_json::json;
+
+fn with_rules(s: &common::Sandbox) {
+    common::set_list(vec![
+        json!({ "id": "a", "name": "Dev", "on": true }),
+        json!({ "id": "f", "name": "Folder", "type": "folder", "children": [
+            json!({ "id": "b", "name": "Staging", "on": true }),
+        ] }),
+        json!({ "id": "c", "name": "Old", "on": false }),
+    ]);
+    s.write(
+        "hosts_content_a.txt",
+        "10.0.0.1 api.test www.test\n10.0.0.1 API.test\nnot an entry\n::1 api.test\n",
+    );
+    s.write(
+        "hosts_content_b.txt",
+        "10.0.0.2 api.test\n10.0.0.2 *.test\n",
+    );
+    s.write("hosts_content_c.txt", "10.0.0.3 www.test\n");
+}
+
+#[test]
+fn counts_entries_hostnames_addresses_and_invalid_lines() {
+    let s = common::sandbox();
+    with_rules(&s);
+    let report = commands::get_report();
+    let ids: Vec<&str> = report.rules.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, ["a", "b", "c"]);
+
+    let a = &report.rules[0];
+    assert_eq!((a.entries, a.hostnames), (3, 2));
+    assert_eq!(a.ips, ["10.0.0.1", "::1"]);
+    assert_eq!(a.invalid_lines, [3]);
+    assert!(a.enabled && a.modified_ms.is_some() && a.applied_ms.is_none());
+    assert!(!report.rules[2].enabled);
+
+    let composed = commands::get_content_of_list(None).unwrap();
+    assert_eq!(report.composed_bytes, Some(composed.len()));
+    assert_eq!(report.compose_error, None);
+}
+
+// only enabled rules conflict, only within an address family, and never
+// through a wildcard
+#[test]
+fn reports_conflicts_with_other_enabled_rules() {
+    let s = common::sandbox();
+    with_rules(&s);
+    let report = commands::get_report();
+    let a = &report.rules[0];
+    assert_eq!(a.conflicts.len(), 1);
+    let conflict = &a.conflicts[0];
+    assert_eq!(
+        (conflict.hostname.as_str(), conflict.ip.as_str()),
+        ("api.test", "10.0.0.2")
+    );
+    assert_eq!(
+        (conflict.rule_id.as_str(), conflict.title.as_str()),
+        ("b", "Staging")
+    );
+    assert_eq!(report.rules[1].conflicts[0].rule_id, "a");
+    // a disabled rule still sees what it would clash with
+    assert_eq!(report.rules[2].conflicts[0].hostname, "www.test");
+}
+
+#[test]
+fn remembers_when_rules_were_last_applied() {
+    let s = common::sandbox();
+    with_rules(&s);
+    assert!(commands::apply_system_hosts(None)["success"] == true);
+    let report = commands::get_report();
+    assert!(report.rules[0].applied_ms.is_some());
+    assert!(report.rules[1].applied_ms.is_some());
+    assert!(report.rules[2].applied_ms.is_none());
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::resolver::{self, Server, Table};
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+const A: u16 = 1;
+const MX: u16 = 15;
+const AAAA: u16 = 28;
+
+fn query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut q = id.to_be_bytes().to_vec();
+    q.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        q.push(label.len() as u8);
+        q.extend_from_slice(label.as_bytes());
+    }
+    q.push(0);
+    q.extend_from_slice(&qtype.to_be_bytes());
+    q.extend_from_slice(&1u16.to_be_bytes());
+    q
+}
+
+struct Reply {
+    id: u16,
+    rcode: u8,
+    addrs: Vec<IpAddr>,
+}
+
+// Sends one query to `server` and decodes the answers it gets back.
+fn ask(server: SocketAddr, name: &str, qtype: u16) -> Reply {
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let q = query(0x1234, name, qtype);
+    client.send_to(&q, server).unwrap();
+    let mut buf = [0u8; 512];
+    let (n, _) = client.recv_from(&mut buf).unwrap();
+    let msg = &buf[..n];
+    let count = u16::from_be_bytes([msg[6], msg[7]]);
+    let mut pos = q.len();
+    let mut addrs = Vec::new();
+    for _ in 0..count {
+        let len = u16::from_be_bytes([msg[pos + 10], msg[pos + 11]]) as usize;
+        let data = &msg[pos + 12..pos + 12 + len];
+        addrs.push(match len {
+            4 => IpAddr::from(<[u8; 4]>::try_from(data).unwrap()),
+            _ => IpAddr::from(<[u8; 16]>::try_from(data).unwrap()),
+        });
+        pos += 12 + len;
+    }
+    Reply {
+        id: u16::from_be_bytes([msg[0], msg[1]]),
+        rcode: msg[3] & 0x0F,
+        addrs,
+    }
+}
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+// An upstream that answers everything with NXDOMAIN.
+fn fake_upstream() -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+            let mut reply = buf[..n].to_vec();
+            reply[2] |= 0x80;
+            reply[3] = 0x83;
+            socket.send_to(&reply, peer).unwrap();
+        }
+    });
+    addr
+}
+
+fn serve(content: &str) -> Server {
+    Server::start(
+        "127.0.0.1:0".parse().unwrap(),
+        fake_upstream(),
+        Table::from_hosts(content),
+    )
+    .unwrap()
+}
+
+#[test]
+fn answers_mapped_names_by_family() {
+    let server = serve("10.0.0.1 Api.Test\n::1 api.test\n0.0.0.0 ads.test\n");
+    let addr = server.local_addr();
+
+    let r = ask(addr, "api.test", A);
+    assert_eq!((r.id, r.rcode), (0x1234, 0));
+    assert_eq!(r.addrs, vec![ip("10.0.0.1")]);
+    assert_eq!(ask(addr, "API.test", AAAA).addrs, vec![ip("::1")]);
+    // mapped names never leak to the upstream, whatever the record type
+    let r = ask(addr, "ads.test", AAAA);
+    assert_eq!((r.rcode, r.addrs.len()), (0, 0));
+    assert_eq!(ask(addr, "api.test", MX).rcode, 0);
+}
+
+#[test]
+fn wildcards_cover_subdomains_and_lose_to_exact_names() {
+    let server = serve("10.1.1.1 *.dev.local\n10.2.2.2 api.dev.local\n10.3.3.3 *.x.dev.local\n");
+    let addr = server.local_addr();
+    assert_eq!(ask(addr, "a.dev.local", A).addrs, vec![ip("10.1.1.1")]);
+    assert_eq!(ask(addr, "a.b.dev.local", A).addrs, vec![ip("10.1.1.1")]);
+    assert_eq!(ask(addr, "api.dev.local", A).addrs, vec![ip("10.2.2.2")]);
+    assert_eq!(ask(addr, "b.x.dev.local", A).addrs, vec![ip("10.3.3.3")]);
+    // the wildcard does not cover the name itself
+    assert_eq!(ask(addr, "dev.local", A).rcode, 3);
+}
+
+#[test]
+fn forwards_other_names_and_picks_up_new_tables() {
+    let server = serve("");
+    let addr = server.local_addr();
+    let r = ask(addr, "example.test", A);
+    assert_eq!((r.id, r.rcode), (0x1234, 3));
+
+    server.set_table(Table::from_hosts("10.9.9.9 example.test\n"));
+    assert_eq!(ask(addr, "example.test", A).addrs, vec![ip("10.9.9.9")]);
+}
+
+// a burst beyond the forwarding queue is answered at once instead of piling
+// up threads on an upstream that never answers
+#[test]
+fn overflowing_forwards_get_servfail() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = Server::start(
+        "127.0.0.1:0".parse().unwrap(),
+        silent.local_addr().unwrap(),
+        Table::default(),
+    )
+    .unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let q = query(7, "example.test", A);
+    for _ in 0..resolver::FORWARD_WORKERS + resolver::FORWARD_QUEUE + 1 {
+        client.send_to(&q, server.local_addr()).unwrap();
+    }
+    let mut buf = [0u8; 512];
+    let (n, _) = client.recv_from(&mut buf).unwrap();
+    assert!(n >= 12);
+    assert_eq!(buf[3] & 0x0F, 2);
+}
+
+#[test]
+fn apply_in_resolver_mode_leaves_hosts_alone() {
+    let s = common::sandbox();
+    let port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut settings = commands::get_settings().stored;
+    settings.resolver_mode = true;
+    settings.resolver_port = port;
+    settings.resolver_upstream = fake_upstream().to_string();
+    commands::set_settings(settings.clone()).unwrap();
+
+    common::set_list(vec![json!({ "id": "a", "on": true })]);
+    s.write("hosts_content_a.txt", "10.0.0.7 app.test\n");
+    let res = commands::apply_system_hosts(None);
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(s.read_hosts(), common::SYSTEM_HOSTS);
+
+    let server: SocketAddr = ([127, 0, 0, 1], port).into();
+    assert_eq!(ask(server, "app.test", A).addrs, vec![ip("10.0.0.7")]);
+
+    settings.resolver_mode = false;
+    commands::set_settings(settings).unwrap();
+    // the port is free again
+    UdpSocket::bind(server).unwrap();
+}
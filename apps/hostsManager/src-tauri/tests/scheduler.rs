@@ -0,0 +1,104 @@
+mod common;
+
+use chrono::{DateTime, Local, TimeZone};
+use hostsmanager_lib::{commands, scheduler};
+use serde_json::{json, Value};
+
+// 2024-01-01 was a Monday
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    Local
+        .with_ymd_and_hms(2024, 1, day, hour, minute, 0)
+        .unwrap()
+}
+
+fn rule(schedule: Value) -> Vec<Value> {
+    vec![json!({ "id": "r", "name": "Rule", "on": false, "schedule": schedule })]
+}
+
+fn on(list: &[Value]) -> bool {
+    list[0]["on"] == true
+}
+
+#[test]
+fn windows_may_wrap_midnight() {
+    let mut list = rule(json!({ "windows": [{ "start": "22:00", "end": "06:00" }] }));
+    assert!(!scheduler::evaluate(&mut list, &at(1, 12, 0)));
+    assert!(scheduler::evaluate(&mut list, &at(1, 23, 30)));
+    assert!(on(&list));
+    assert!(!scheduler::evaluate(&mut list, &at(2, 5, 59)));
+    assert!(scheduler::evaluate(&mut list, &at(2, 6, 0)));
+    assert!(!on(&list));
+}
+
+#[test]
+fn weekdays_and_windows_both_apply() {
+    let mut list = rule(json!({
+        "weekdays": [6, 7],
+        "windows": [{ "start": "09:00", "end": "18:00" }]
+    }));
+    scheduler::evaluate(&mut list, &at(1, 10, 0));
+    assert!(!on(&list));
+    scheduler::evaluate(&mut list, &at(6, 8, 59));
+    assert!(!on(&list));
+    scheduler::evaluate(&mut list, &at(6, 9, 0));
+    assert!(on(&list));
+    // a malformed window never matches
+    let mut list = rule(json!({ "windows": [{ "start": "9", "end": "25:00" }] }));
+    scheduler::evaluate(&mut list, &at(1, 10, 0));
+    assert!(!on(&list));
+}
+
+#[test]
+fn manual_toggles_hold_until_the_next_boundary() {
+    let mut list = rule(json!({ "windows": [{ "start": "09:00", "end": "18:00" }] }));
+    scheduler::evaluate(&mut list, &at(1, 10, 0));
+    assert!(on(&list));
+    list[0]["on"] = json!(false);
+    assert!(!scheduler::evaluate(&mut list, &at(1, 11, 0)));
+    assert!(!on(&list));
+    // the window closes on a rule that is already off
+    assert!(!scheduler::evaluate(&mut list, &at(1, 18, 0)));
+    // switched on out of hours, it stays on until the next window ends
+    list[0]["on"] = json!(true);
+    assert!(!scheduler::evaluate(&mut list, &at(1, 20, 0)));
+    assert!(!scheduler::evaluate(&mut list, &at(2, 9, 0)));
+    assert!(scheduler::evaluate(&mut list, &at(2, 18, 0)));
+    assert!(!on(&list));
+}
+
+#[test]
+fn expiry_switches_off_once_but_not_pinned_rules() {
+    let expires = at(1, 12, 0).timestamp_millis();
+    let mut list = vec![
+        json!({ "id": "a", "name": "A", "on": true, "schedule": { "expires_at_ms": expires } }),
+        json!({ "id": "f", "name": "F", "type": "folder", "children": [
+            { "id": "p", "name": "P", "on": true, "pinned": true,
+              "schedule": { "expires_at_ms": expires } }
+        ] }),
+    ];
+    assert!(!scheduler::evaluate(&mut list, &at(1, 11, 59)));
+    assert!(scheduler::evaluate(&mut list, &at(1, 12, 0)));
+    assert_eq!(list[0]["on"], false);
+    assert!(list[0]["schedule"].get("expires_at_ms").is_none());
+    assert_eq!(list[1]["children"][0]["on"], true);
+    assert!(list[1]["children"][0]["schedule"]
+        .get("expires_at_ms")
+        .is_none());
+    // switched back on by hand, it stays on
+    list[0]["on"] = json!(true);
+    assert!(!scheduler::evaluate(&mut list, &at(1, 13, 0)));
+}
+
+#[test]
+fn tick_saves_and_applies_only_when_a_rule_is_switched() {
+    let s = common::sandbox();
+    common::set_list(rule(
+        json!({ "windows": [{ "start": "09:00", "end": "18:00" }] }),
+    ));
+    s.write("hosts_content_r.txt", "10.0.0.1 scheduled.test\n");
+    let res = scheduler::tick(&at(1, 10, 0)).unwrap();
+    assert_eq!(res["success"], true);
+    assert!(s.read_hosts().contains("10.0.0.1 scheduled.test"));
+    assert_eq!(commands::get_list()[0]["on"], true);
+    assert!(scheduler::tick(&at(1, 11, 0)).is_none());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use serde_json::json;
+
+fn with_rules(s: &common::Sandbox) {
+    common::set_list(vec![
+        json!({ "id": "dev", "name": "Dev API", "on": true }),
+        json!({ "id": "f", "name": "Folder", "type": "folder", "children": [
+            { "id": "v6", "name": "IPv6", "on": false }
+        ] }),
+    ]);
+    s.write(
+        "hosts_content_dev.txt",
+        "# dev\n10.1.2.3 api.dev.test\n192.168.1.5 NAS.lan\nbroken line\n",
+    );
+    s.write(
+        "hosts_content_v6.txt",
+        "fd00::5 api6.dev.test\n::1 local.test\n",
+    );
+}
+
+// (rule id, line) of every hit
+fn hits(query: &str) -> Vec<(String, Option<usize>)> {
+    commands::search_rules(query.into())
+        .unwrap()
+        .into_iter()
+        .map(|h| (h.rule_id, h.line))
+        .collect()
+}
+
+fn hit(id: &str, line: Option<usize>) -> (String, Option<usize>) {
+    (id.to_string(), line)
+}
+
+#[test]
+fn cidr_ranges_match_entry_addresses() {
+    let s = common::sandbox();
+    with_rules(&s);
+    assert_eq!(hits("10.0.0.0/8"), vec![hit("dev", Some(2))]);
+    assert_eq!(hits("192.168.1.0/24"), vec![hit("dev", Some(3))]);
+    assert_eq!(hits("0.0.0.0/0").len(), 2);
+    assert_eq!(hits("fd00::/8"), vec![hit("v6", Some(1))]);
+    // the families never mix
+    assert_eq!(hits("::/0").len(), 2);
+    assert!(commands::search_rules("10.0.0.0/33".into()).is_err());
+}
+
+#[test]
+fn exact_addresses_match_entries_only() {
+    let s = common::sandbox();
+    with_rules(&s);
+    assert_eq!(hits("10.1.2.3"), vec![hit("dev", Some(2))]);
+    assert_eq!(hits("::1"), vec![hit("v6", Some(2))]);
+    assert_eq!(hits("10.1.2.4"), vec![]);
+}
+
+#[test]
+fn regexes_match_titles_and_raw_lines() {
+    let s = common::sandbox();
+    with_rules(&s);
+    assert_eq!(
+        hits("/^dev/"),
+        vec![hit("dev", None)],
+        "titles match, comments do not"
+    );
+    assert_eq!(
+        hits("/api6?\\.dev/"),
+        vec![hit("dev", Some(2)), hit("v6", Some(1))]
+    );
+    // invalid lines are searchable too, case-insensitively
+    assert_eq!(hits("/BROKEN/"), vec![hit("dev", Some(4))]);
+    assert!(commands::search_rules("/[/".into()).is_err());
+}
+
+#[test]
+fn plain_text_matches_hostnames_and_titles() {
+    let s = common::sandbox();
+    with_rules(&s);
+    let found = commands::search_rules("nas".into()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        (found[0].line, found[0].entry.as_str()),
+        (Some(3), "192.168.1.5 NAS.lan")
+    );
+    assert!(found[0].enabled);
+    assert_eq!(
+        hits("API"),
+        vec![hit("dev", None), hit("dev", Some(2)), hit("v6", Some(1))]
+    );
+    assert!(commands::search_rules("  ".into()).is_err());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::deeplink::{self, Link};
+use hostsmanager_lib::share::{self, SharedRule};
+use serde_json::json;
+
+const CONTENT: &str = "# staging\n10.0.0.1 api.test\n10.0.0.2 web.test\nnot an entry\n";
+
+fn with_rule(s: &common::Sandbox) {
+    common::set_list(vec![
+        json!({ "id": "a", "name": "Staging", "on": true }),
+        json!({ "id": "f", "name": "Folder", "type": "folder", "children": [] }),
+    ]);
+    s.write("hosts_content_a.txt", CONTENT);
+}
+
+#[test]
+fn every_form_of_a_share_previews_the_same_rule() {
+    let s = common::sandbox();
+    with_rule(&s);
+    let shared = commands::share_rule("a".into()).unwrap();
+    assert!(shared.payload.starts_with("hm1."));
+    assert!(shared
+        .payload
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)));
+    assert_eq!(
+        deeplink::parse(&shared.link),
+        Ok(Link::Import {
+            data: shared.payload.clone()
+        })
+    );
+    assert!(shared.text.lines().all(|l| l.len() <= 64));
+
+    // chat clients rewrap and indent pasted blocks
+    let pasted = shared.text.replace('\n', "\n  ");
+    for text in [&shared.payload, &shared.link, &pasted] {
+        let preview = commands::preview_shared_rule(text.clone()).unwrap();
+        assert_eq!(preview.title, "Staging");
+        assert_eq!(preview.content, CONTENT);
+        assert_eq!(preview.entries, 2);
+        assert_eq!(preview.existing, vec!["a".to_string()]);
+        let codes: Vec<&str> = preview.warnings.iter().map(|w| w.code).collect();
+        assert!(codes.contains(&"invalid_line"));
+    }
+}
+
+#[test]
+fn import_adds_a_disabled_copy() {
+    let s = common::sandbox();
+    with_rule(&s);
+    let shared = commands::share_rule("a".into()).unwrap();
+    let item = commands::import_shared_rule(shared.link, Some("f".into())).unwrap();
+    let id = item["id"].as_str().unwrap().to_string();
+    assert_ne!(id, "a");
+    assert_eq!(item["name"], "Staging");
+    assert_eq!(item["on"], false);
+    assert_eq!(commands::get_list()[1]["children"][0]["id"], id.as_str());
+    assert_eq!(commands::get_hosts_content(id), CONTENT);
+}
+
+#[test]
+fn damaged_payloads_are_rejected() {
+    let payload = share::encode(&SharedRule {
+        title: "T".into(),
+        content: "10.0.0.1 a.test\n".into(),
+    });
+    let (head, sum) = payload.rsplit_once('.').unwrap();
+    let flipped = if sum.starts_with('A') { 'B' } else { 'A' };
+    let bad_sum = format!("{}.{}{}", head, flipped, &sum[1..]);
+    let code = |text: &str| share::decode(text).unwrap_err().code();
+    assert_eq!(code(&bad_sum), "checksum");
+    assert_eq!(code(&payload[..payload.len() / 2]), "malformed");
+    assert_eq!(code(&payload.replacen("hm1", "hm9", 1)), "version");
+    assert_eq!(code("hostsmanager://apply"), "malformed");
+    assert_eq!(code("-----BEGIN HOSTSMANAGER RULE-----\nhm1."), "malformed");
+}
+
+#[test]
+fn folders_cannot_be_shared() {
+    let s = common::sandbox();
+    with_rule(&s);
+    assert_eq!(
+        commands::share_rule("f".into()).unwrap_err().code(),
+        "folder"
+    );
+    assert_eq!(
+        commands::share_rule("nope".into()).unwrap_err().code(),
+        "not_found"
+    );
+}
+
+#[test]
+fn a_system_rule_shares_the_live_hosts_file() {
+    let s = common::sandbox();
+    s.write(
+        "list.json",
+        r#"[{"id":"sys","name":"System","system":true}]"#,
+    );
+    s.write("hosts_content_sys.txt", "10.9.9.9 stale.test\n");
+    let shared = commands::share_rule("sys".into()).unwrap();
+    let preview = commands::preview_shared_rule(shared.payload).unwrap();
+    assert_eq!(preview.content, common::SYSTEM_HOSTS);
+}
@@ -0,0 +1,281 @@
+mod common;
+
+use hostsmanager_lib::commands;
+use hostsmanager_lib::sync::{self, Prefer, Pulled, SyncError};
+use serde_json::{json, Map, Value};
+use std::env;
+use std::process::Command;
+
+fn git(dir: &std::path::Path, args: &[&str]) -> String {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("git");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+// Turns sync on with a fresh bare repository as the remote.
+fn with_remote(s: &common::Sandbox) {
+    let remote = s.root.path().join("remote.git");
+    git(
+        s.root.path(),
+        &["init", "-q", "--bare", "-b", "main", "remote.git"],
+    );
+    let mut settings = commands::get_settings().stored;
+    settings.sync_enabled = true;
+    settings.sync_remote = Some(remote.to_string_lossy().into_owned());
+    commands::set_settings(settings).unwrap();
+}
+
+// Another copy of the data directory; settings (and so the remote) are shared.
+fn machine(s: &common::Sandbox, name: &str) {
+    env::set_var("HOSTSMANAGER_DATA_DIR", s.root.path().join(name));
+}
+
+fn add_rule(name: &str, content: &str) -> String {
+    let item =
+        commands::create_item(None, json!({ "name": name }), None, Some(content.into())).unwrap();
+    item["id"].as_str().unwrap().to_string()
+}
+
+fn patch(v: Value) -> Map<String, Value> {
+    v.as_object().unwrap().clone()
+}
+
+fn subjects(s: &common::Sandbox) -> Vec<String> {
+    git(&s.data_dir(), &["log", "--format=%s"])
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn changes_are_committed_one_by_one() {
+    let s = common::sandbox();
+    common::set_list(vec![json!({ "id": "old", "name": "Old", "on": true })]);
+    with_remote(&s);
+    let id = add_rule("Dev", "10.0.0.1 dev.test\n");
+    commands::update_item(id.clone(), patch(json!({ "on": true }))).unwrap();
+    assert!(commands::set_hosts_content(id.clone(), "10.0.0.2 dev.test\n".into()).unwrap());
+    let mut list = commands::get_list();
+    list.retain(|item| item["id"] != "old");
+    commands::set_list(list, None).unwrap();
+    commands::delete_item(id).unwrap();
+
+    assert_eq!(
+        subjects(&s),
+        vec![
+            "Delete Dev",
+            "Delete Old",
+            "Edit content of Dev",
+            "Enable Dev",
+            "Add Dev",
+            "Start syncing rules",
+        ]
+    );
+    // only the rules are tracked
+    let tracked = git(&s.data_dir(), &["ls-files"]);
+    assert_eq!(tracked, ".gitignore\nlist.json\n");
+}
+
+#[test]
+fn other_settings_do_not_commit() {
+    let s = common::sandbox();
+    with_remote(&s);
+    // an edit made outside the app waits for the next change of the rules
+    s.write("variables.json", r#"{"host":"10.0.0.1"}"#);
+    let mut settings = commands::get_settings().stored;
+    settings.flush_dns = !settings.flush_dns;
+    commands::set_settings(settings).unwrap();
+    assert_eq!(subjects(&s), vec!["Start syncing rules"]);
+}
+
+#[test]
+fn push_and_pull_carry_rules_between_machines() {
+    let s = common::sandbox();
+    with_remote(&s);
+    let id = add_rule("Dev", "10.0.0.1 dev.test\n");
+    commands::sync_push().unwrap();
+
+    machine(&s, "laptop");
+    commands::sync_pull(None).unwrap();
+    assert_eq!(
+        commands::get_hosts_content(id.clone()),
+        "10.0.0.1 dev.test\n"
+    );
+    commands::update_item(id.clone(), patch(json!({ "on": true }))).unwrap();
+    commands::sync_push().unwrap();
+
+    machine(&s, "data");
+    assert_eq!(commands::sync_pull(None).unwrap(), Pulled::FastForward);
+    assert_eq!(commands::get_list()[0]["on"], true);
+    assert_eq!(commands::sync_pull(None).unwrap(), Pulled::UpToDate);
+}
+
+#[test]
+fn push_needs_a_pull_after_remote_changes() {
+    let s = common::sandbox();
+    with_remote(&s);
+    add_rule("Dev", "");
+    commands::sync_push().unwrap();
+    machine(&s, "laptop");
+    add_rule("Laptop", "");
+    let err = commands::sync_push().unwrap_err();
+    assert_eq!(err.code(), "behind");
+    commands::sync_pull(None).unwrap();
+    commands::sync_push().unwrap();
+}
+
+#[test]
+fn edits_of_different_rules_merge() {
+    let s = common::sandbox();
+    with_remote(&s);
+    let a = add_rule("A", "10.0.0.1 a.test\n");
+    let b = add_rule("B", "10.0.0.2 b.test\n");
+    commands::sync_push().unwrap();
+    machine(&s, "laptop");
+    commands::sync_pull(None).unwrap();
+    commands::update_item(b.clone(), patch(json!({ "on": true }))).unwrap();
+    assert!(
+        commands::set_hosts_content(b.clone(), "10.0.0.2 b.test\n10.0.0.3 c.test\n".into())
+            .unwrap()
+    );
+    let c = add_rule("C", "");
+    commands::sync_push().unwrap();
+
+    machine(&s, "data");
+    commands::update_item(a.clone(), patch(json!({ "name": "A2" }))).unwrap();
+    assert_eq!(commands::sync_pull(None).unwrap(), Pulled::Merged);
+    let list = commands::get_list();
+    let names: Vec<&str> = list.iter().map(|i| i["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["A2", "B", "C"]);
+    assert_eq!(list[1]["on"], true);
+    assert_eq!(list[2]["id"], c.as_str());
+    assert_eq!(
+        commands::get_hosts_content(b),
+        "10.0.0.2 b.test\n10.0.0.3 c.test\n"
+    );
+    commands::sync_push().unwrap();
+}
+
+#[test]
+fn edits_of_the_same_rule_conflict_until_a_side_is_chosen() {
+    let s = common::sandbox();
+    with_remote(&s);
+    let a = add_rule("A", "10.0.0.1 a.test\n");
+    let b = add_rule("B", "");
+    commands::sync_push().unwrap();
+    machine(&s, "laptop");
+    commands::sync_pull(None).unwrap();
+    commands::update_item(a.clone(), patch(json!({ "name": "Remote A" }))).unwrap();
+    assert!(commands::set_hosts_content(b.clone(), "10.9.9.9 b.test\n".into()).unwrap());
+    commands::sync_push().unwrap();
+
+    machine(&s, "data");
+    commands::update_item(a.clone(), patch(json!({ "name": "Local A" }))).unwrap();
+    assert!(commands::set_hosts_content(b.clone(), "10.1.1.1 b.test\n".into()).unwrap());
+    let list_before = std::fs::read(s.data_dir().join("list.json")).unwrap();
+    let Err(SyncError::Conflict(conflicts)) = commands::sync_pull(None) else {
+        panic!("expected a conflict");
+    };
+    let mut found: Vec<(Option<&str>, &str)> = conflicts
+        .iter()
+        .map(|c| (c.rule_id.as_deref(), c.file.as_str()))
+        .collect();
+    found.sort();
+    let content_file = format!("hosts_content_{}.txt", b);
+    let mut expected = vec![
+        (Some(a.as_str()), "list.json"),
+        (Some(b.as_str()), content_file.as_str()),
+    ];
+    expected.sort();
+    assert_eq!(found, expected);
+    // nothing was touched
+    assert_eq!(
+        std::fs::read(s.data_dir().join("list.json")).unwrap(),
+        list_before
+    );
+    assert_eq!(commands::get_hosts_content(b.clone()), "10.1.1.1 b.test\n");
+
+    assert_eq!(
+        commands::sync_pull(Some(Prefer::Remote)).unwrap(),
+        Pulled::Merged
+    );
+    assert_eq!(commands::get_list()[0]["name"], "Remote A");
+    assert_eq!(commands::get_hosts_content(b), "10.9.9.9 b.test\n");
+}
+
+#[test]
+fn a_folder_deleted_remotely_keeps_rules_added_to_it_here() {
+    let s = common::sandbox();
+    with_remote(&s);
+    let folder = commands::create_item(
+        None,
+        json!({ "name": "Staging", "type": "folder" }),
+        None,
+        None,
+    )
+    .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    commands::sync_push().unwrap();
+    machine(&s, "laptop");
+    commands::sync_pull(None).unwrap();
+    commands::delete_item(folder.clone()).unwrap();
+    commands::sync_push().unwrap();
+
+    machine(&s, "data");
+    let child = commands::create_item(
+        Some(folder.clone()),
+        json!({ "name": "API" }),
+        None,
+        Some("10.0.0.1 api.test\n".into()),
+    )
+    .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let Err(SyncError::Conflict(conflicts)) = commands::sync_pull(None) else {
+        panic!("expected a conflict");
+    };
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].rule_id.as_deref(), Some(folder.as_str()));
+    assert_eq!(commands::get_list()[0]["children"][0]["id"], child.as_str());
+
+    assert_eq!(
+        commands::sync_pull(Some(Prefer::Local)).unwrap(),
+        Pulled::Merged
+    );
+    let list = commands::get_list();
+    assert_eq!(list[0]["id"], folder.as_str());
+    assert_eq!(list[0]["children"][0]["id"], child.as_str());
+}
+
+#[test]
+fn a_run_of_content_edits_is_one_commit() {
+    let s = common::sandbox();
+    with_remote(&s);
+    let id = add_rule("Dev", "");
+    for content in ["1", "10.0", "10.0.0.1", "10.0.0.1 dev.test\n"] {
+        assert!(commands::set_hosts_content(id.clone(), content.into()).unwrap());
+    }
+    assert_eq!(subjects(&s), vec!["Add Dev", "Start syncing rules"]);
+    std::thread::sleep(sync::EDIT_IDLE + std::time::Duration::from_millis(500));
+    assert_eq!(
+        subjects(&s),
+        vec!["Edit content of Dev", "Add Dev", "Start syncing rules"]
+    );
+    // a push does not wait for the editor to go idle
+    assert!(commands::set_hosts_content(id, "10.0.0.2 dev.test\n".into()).unwrap());
+    commands::sync_push().unwrap();
+    assert_eq!(subjects(&s)[0], "Edit content of Dev");
+    assert_eq!(subjects(&s).len(), 4);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use hostsmanager_lib::commands;
+
+fn codes(content: &str) -> Vec<(usize, &'static str)> {
+    commands::validate_hosts_content(content.into())
+        .into_iter()
+        .map(|w| (w.line, w.code))
+        .collect()
+}
+
+#[test]
+fn invalid_lines_and_patterns_are_reported() {
+    let _s = common::sandbox();
+    assert_eq!(
+        codes("# comment\n\nnot-an-ip host.test\n10.0.0.1\n10.0.0.1 *.dev.test /[/\n"),
+        vec![
+            (3, "invalid_line"),
+            (4, "invalid_line"),
+            (5, "pattern_host"),
+            (5, "invalid_pattern"),
+        ]
+    );
+}
+
+// only blocking and loopback mappings need their other-family counterpart
+#[test]
+fn one_family_warnings_are_limited_to_local_addresses() {
+    let _s = common::sandbox();
+    assert_eq!(
+        codes("192.168.1.10 nas.lan\n93.184.216.34 example.test\n2001:db8::1 v6.test\n"),
+        vec![]
+    );
+    assert_eq!(
+        codes("127.0.0.1 app.test\n0.0.0.0 ads.test\n::1 only6.test\n"),
+        vec![(1, "ipv4_only"), (2, "ipv4_only"), (3, "ipv6_only")]
+    );
+    // any mapping of the other family covers it, in either order
+    assert_eq!(
+        codes("0.0.0.0 ads.test\n127.0.0.1 App.test\n:: ads.test\n2001:db8::1 app.test\n"),
+        vec![]
+    );
+}
@@ -20,12 +20,231 @@ export async function ping(): Promise<string> {
 export async function getList(): Promise<JsonValue[]> {
   return invokeCmd('get_list');
 }
-export async function setList(v: JsonValue[]): Promise<boolean> {
-  return invokeCmd('set_list', { v });
+export type ListSnapshot = { list: JsonValue[]; revision: string };
+
+export async function getListWithRevision(): Promise<ListSnapshot> {
+  return invokeCmd('get_list_with_revision');
+}
+
+export async function getListRevision(): Promise<string> {
+  return invokeCmd('get_list_revision');
+}
+
+// Rejects with a StoreError; `code: 'conflict'` means the list changed since
+// `revision` was read, `system` / `pinned` that the change breaks the flag of
+// rule `rule_id`.
+export type StoreError = {
+  code: 'conflict' | 'io' | 'system' | 'pinned';
+  message: string;
+  revision?: string;
+  rule_id?: string;
+};
+
+export async function setList(
+  v: JsonValue[],
+  revision?: string | null
+): Promise<string> {
+  return invokeCmd('set_list', { v, revision });
+}
+
+// Tree edits; each rejects with `{ code, message }` where code is one of
+// not_found | not_a_folder | into_itself | invalid | conflict | io | system |
+// pinned.
+export type ItemError = { code: string; message: string };
+
+export async function createItem(
+  parent: string | null,
+  item: Partial<JsonValue>,
+  index?: number | null,
+  content?: string | null
+): Promise<JsonValue> {
+  return invokeCmd('create_item', { parent, item, index, content });
+}
+
+export async function updateItem(
+  id: string,
+  patch: Record<string, JsonValue>
+): Promise<JsonValue> {
+  return invokeCmd('update_item', { id, patch });
 }
 
-export async function getContentOfList(): Promise<string> {
-  return invokeCmd('get_content_of_list');
+export async function moveItem(
+  id: string,
+  parent: string | null,
+  index?: number | null
+): Promise<void> {
+  return invokeCmd('move_item', { id, parent, index });
+}
+
+export async function deleteItem(id: string): Promise<string[]> {
+  return invokeCmd('delete_item', { id });
+}
+
+export async function duplicateItem(id: string): Promise<JsonValue> {
+  return invokeCmd('duplicate_item', { id });
+}
+
+export type ComposeOptions = {
+  ipv6_companions?: boolean;
+  // leave wildcard/regex hosts unexpanded (what the resolver is given)
+  keep_patterns?: boolean;
+};
+
+export async function getContentOfList(
+  opts?: ComposeOptions | null
+): Promise<string> {
+  return invokeCmd('get_content_of_list', { opts });
+}
+
+export type SourceMapEntry = {
+  line: number;
+  rule_id: string;
+  rule_line: number | null;
+};
+
+export type Composition = {
+  content: string;
+  source_map: SourceMapEntry[];
+  unresolved: UnresolvedVariable[];
+};
+
+export async function getComposition(
+  opts?: ComposeOptions | null
+): Promise<Composition> {
+  return invokeCmd('get_composition', { opts });
+}
+
+export type ExportFormat = 'dnsmasq' | 'unbound' | 'coredns' | 'json';
+
+// The enabled rules as DNS server config (or a hostname -> addresses JSON).
+export async function exportAs(format: ExportFormat): Promise<string> {
+  return invokeCmd('export_as', { format });
+}
+
+export type ValidationWarning = {
+  line: number;
+  code: 'invalid_line' | 'ipv4_only' | 'ipv6_only' | 'pattern_host' | 'invalid_pattern';
+  message: string;
+};
+
+export async function validateHostsContent(
+  content: string
+): Promise<ValidationWarning[]> {
+  return invokeCmd('validate_hosts_content', { content });
+}
+
+// One rule as `hm1.<data>.<check>`, as a hostsmanager://import link and as a
+// text block for chat. Errors are `{ code, message }` with code one of
+// malformed | version | checksum | too_large | folder, or an ItemError code.
+export type SharedRule = { payload: string; link: string; text: string };
+
+export type SharePreview = {
+  title: string;
+  content: string;
+  entries: number;
+  warnings: ValidationWarning[];
+  existing: string[]; // ids of rules with the same title
+};
+
+export async function shareRule(id: string): Promise<SharedRule> {
+  return invokeCmd('share_rule', { id });
+}
+
+// `text` may be the payload, the link or the text block.
+export async function previewSharedRule(text: string): Promise<SharePreview> {
+  return invokeCmd('preview_shared_rule', { text });
+}
+
+export async function importSharedRule(
+  text: string,
+  parent?: string | null
+): Promise<JsonValue> {
+  return invokeCmd('import_shared_rule', { text, parent });
+}
+
+// Another enabled rule mapping `hostname` to a different address (`ip`) of
+// the same family.
+export type RuleConflict = {
+  hostname: string;
+  ip: string;
+  rule_id: string;
+  title: string;
+};
+
+export type RuleReport = {
+  id: string;
+  title: string;
+  enabled: boolean; // composed on the next apply
+  entries: number;
+  hostnames: number; // distinct
+  ips: string[];
+  invalid_lines: number[];
+  conflicts: RuleConflict[];
+  modified_ms: number | null;
+  applied_ms: number | null;
+};
+
+export type Report = {
+  rules: RuleReport[];
+  // null when composition fails, with the reason in compose_error
+  composed_bytes: number | null;
+  composed_lines: number | null;
+  compose_error: string | null;
+};
+
+export async function getReport(): Promise<Report> {
+  return invokeCmd('get_report');
+}
+
+export type Variables = Record<string, string>;
+
+export type UnresolvedVariable = {
+  rule_id: string;
+  title: string;
+  line: number;
+  name: string;
+};
+
+export async function getVariables(): Promise<Variables> {
+  return invokeCmd('get_variables');
+}
+
+export async function setVariables(v: Variables): Promise<boolean> {
+  return invokeCmd('set_variables', { v });
+}
+
+export async function checkVariables(): Promise<UnresolvedVariable[]> {
+  return invokeCmd('check_variables');
+}
+
+export type SearchHit = {
+  rule_id: string;
+  title: string;
+  line: number | null;
+  enabled: boolean;
+  entry: string;
+};
+
+export async function searchRules(query: string): Promise<SearchHit[]> {
+  return invokeCmd('search_rules', { query });
+}
+
+export type BlocklistFormat = 'auto' | 'hosts' | 'adblock' | 'domains';
+
+export async function refreshRemote(id: string): Promise<number> {
+  return invokeCmd('refresh_remote', { id });
+}
+
+export async function importBlocklist(
+  id: string,
+  text: string,
+  format?: BlocklistFormat | null
+): Promise<number> {
+  return invokeCmd('import_blocklist', { id, text, format });
+}
+
+export async function getPathOfSystemHosts(): Promise<string> {
+  return invokeCmd('get_path_of_system_hosts');
 }
 
 export async function getSystemHosts(): Promise<string> {
@@ -36,6 +255,14 @@ export async function getHostsContent(id: string): Promise<string> {
   return invokeCmd('get_hosts_content', { id });
 }
 
+// Resolves to false when the file could not be written; rejects with
+// `{ code: 'system' | 'locked', message, rule_id }` for a flagged rule.
+export type FlagError = {
+  code: 'system' | 'locked' | 'pinned';
+  message: string;
+  rule_id: string;
+};
+
 export async function setHostsContent(
   id: string,
   content: string
@@ -50,6 +277,123 @@ export async function setSystemHosts(
   return invokeCmd('set_system_hosts', { content, opts });
 }
 
+export async function applySystemHosts(
+  opts?: string | null
+): Promise<JsonValue> {
+  return invokeCmd('apply_system_hosts', { opts });
+}
+
+export type HistoryEntry = {
+  id: string;
+  time_ms: number;
+  size: number;
+};
+
+export async function getHistory(): Promise<HistoryEntry[]> {
+  return invokeCmd('get_history');
+}
+
+export async function getHistoryContent(id: string): Promise<string | null> {
+  return invokeCmd('get_history_content', { id });
+}
+
+export async function rollbackHistory(
+  id: string,
+  opts?: string | null
+): Promise<JsonValue> {
+  return invokeCmd('rollback_history', { id, opts });
+}
+
+export type Settings = {
+  data_dir: string | null;
+  safe_mode: boolean;
+  safe_hosts_path: string | null;
+  write_mode: 'managed_block' | 'whole_file';
+  flush_dns: boolean;
+  history_retention: number;
+  line_endings: 'auto' | 'lf' | 'crlf';
+  close_hides_window: boolean;
+  // serve rules from the embedded DNS resolver instead of writing hosts
+  resolver_mode: boolean;
+  resolver_port: number;
+  resolver_upstream: string;
+  // concrete names that wildcard/regex hosts expand to in the hosts file
+  pattern_hosts: string[];
+  // add ::1 / :: next to 127.x / 0.0.0.0 entries when applying
+  ipv6_companions: boolean;
+  // commit rule changes to a git repo in the data dir, synced with sync_remote
+  sync_enabled: boolean;
+  sync_remote: string | null;
+};
+
+export type SettingsView = {
+  effective: Settings;
+  stored: Settings;
+  env_overrides: (keyof Settings)[];
+};
+
+export async function getSettings(): Promise<SettingsView> {
+  return invokeCmd('get_settings');
+}
+
+export async function setSettings(v: Settings): Promise<SettingsView> {
+  return invokeCmd('set_settings', { v });
+}
+
+// Rejects with `{ code, message }`, code one of disabled | no_remote | behind
+// | conflict | git | io; a conflict lists the rules changed on both sides.
+export type SyncConflict = {
+  rule_id: string | null;
+  title: string;
+  file: string;
+};
+
+export type SyncError = {
+  code: 'disabled' | 'no_remote' | 'behind' | 'conflict' | 'git' | 'io';
+  message: string;
+  conflicts?: SyncConflict[];
+};
+
+export type Pulled = 'up_to_date' | 'fast_forward' | 'merged';
+
+export async function syncPull(
+  prefer?: 'local' | 'remote' | null
+): Promise<Pulled> {
+  return invokeCmd('sync_pull', { prefer });
+}
+
+export async function syncPush(): Promise<void> {
+  return invokeCmd('sync_push');
+}
+
+export type AuditAction = 'write' | 'escalate' | 'rollback' | 'reapply';
+
+export type AuditRecord = {
+  time_ms: number;
+  action: AuditAction;
+  success: boolean;
+  code?: string;
+  message?: string;
+  path: string;
+  content_sha256: string;
+  user: string;
+  pid: number;
+};
+
+export type AuditFilter = {
+  action?: AuditAction;
+  success?: boolean;
+  since_ms?: number;
+  until_ms?: number;
+  limit?: number;
+};
+
+export async function getAuditLog(
+  filter?: AuditFilter | null
+): Promise<AuditRecord[]> {
+  return invokeCmd('get_audit_log', { filter });
+}
+
 export async function closeMainWindow(): Promise<boolean> {
   return invokeCmd('close_main_window');
 }
@@ -58,19 +402,61 @@ export async function quitApp(): Promise<boolean> {
   return invokeCmd('quit');
 }
 
+// Navigation and shared-rule imports parked by the backend's deep-link router;
+// rule names are already resolved to ids, and rule toggles or apply never
+// reach the UI.
+export type DeepLink =
+  | { action: 'openRuleById'; id: string }
+  | { action: 'import'; data: string };
+
+export async function takeDeepLink(): Promise<DeepLink | null> {
+  return invokeCmd('take_deep_link');
+}
+
 
 // default export with snake_case aliases for convenience
 const commands = {
   ping,
   getList,
+  getListWithRevision,
+  getListRevision,
   setList,
+  createItem,
+  updateItem,
+  moveItem,
+  deleteItem,
+  duplicateItem,
   getContentOfList,
+  getComposition,
+  exportAs,
+  getVariables,
+  setVariables,
+  checkVariables,
+  validateHostsContent,
+  shareRule,
+  previewSharedRule,
+  importSharedRule,
+  getReport,
+  searchRules,
+  refreshRemote,
+  importBlocklist,
+  getPathOfSystemHosts,
   getSystemHosts,
   setSystemHosts,
+  applySystemHosts,
+  getHistory,
+  getHistoryContent,
+  rollbackHistory,
+  getSettings,
+  setSettings,
+  syncPull,
+  syncPush,
   getHostsContent,
   setHostsContent,
+  getAuditLog,
   closeMainWindow,
   quitApp,
+  takeDeepLink,
 };
 
 export default commands;
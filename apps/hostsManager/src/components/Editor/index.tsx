@@ -20,7 +20,7 @@ export default function Editor() {
             updateContent(contentId, value);
           }
         }}
-        readOnly={current?.system}
+        readOnly={current?.system || current?.locked}
         value={content || ''}
         height={(size?.height ?? 100) + 'px'}
         extensions={[hosts()]}
@@ -2,7 +2,6 @@ import { useStore } from '../../store';
 import cx from 'clsx';
 import styles from './index.module.less';
 import { Item } from '../../typing';
-import { v4 as uuidV4 } from 'uuid';
 import { useRef, useState } from 'react';
 import { confirm } from '@suite/ui';
 import writeHostsToSystem from '../../utils/writeHostsToSystem';
@@ -66,7 +65,7 @@ function ListItem(props: {
           className={styles.checkbox}
           checked={item.on}
           onCheckedChange={(checked: Checkbox.CheckedState) => onCheck(checked === true)}
-          disabled={item.system}
+          disabled={item.system || (item.pinned && item.on)}
           // 防止冒泡到整行点击
           onClick={(e: React.MouseEvent<HTMLButtonElement>) => e.stopPropagation()}
         >
@@ -150,7 +149,7 @@ export default function List() {
           e.preventDefault();
           const name = (e.target as any).name.value;
           if (name.trim()) {
-            await createItem({ id: uuidV4(), name, on: true });
+            await createItem({ name, on: true });
           }
           (e.target as HTMLFormElement).reset();
         }}
@@ -2,18 +2,7 @@ import { useState, useRef, useEffect } from 'react';
 import { useStore } from '../../store';
 import styles from './index.module.less';
 import { Item } from '../../typing';
-import {
-    findItem,
-    createFile,
-    createFolder,
-    addChild,
-    removeItem,
-    updateItemName,
-    toggleItemOn,
-    pasteNodeWithMap,
-    moveNode,
-} from '../../utils/treeOps';
-import commands from '@/commands';
+import { findItem } from '../../utils/treeOps';
 import writeHostsToSystem from '../../utils/writeHostsToSystem';
 import { confirm, Tree, type TreeDataItem } from '@suite/ui';
 import { SYSTEM_HOST_ITEM } from '@/constants';
@@ -26,7 +15,16 @@ interface ClipboardState {
 }
 
 export default function TreeExplorer() {
-    const { list, userList, current, setCurrent, mutateList } = useStore() as any; // cast for extended fields
+    const {
+        list,
+        current,
+        setCurrent,
+        createItem,
+        updateItem,
+        moveItem,
+        duplicateItem,
+        deleteItem,
+    } = useStore() as any; // cast for extended fields
     const [clipboard, setClipboard] = useState<ClipboardState | null>(null);
     const [renamingId, setRenamingId] = useState<string | null>(null);
     const [renameValue, setRenameValue] = useState<string>('');
@@ -39,10 +37,6 @@ export default function TreeExplorer() {
         }
     }, [renamingId]);
 
-    const apply = async (next: Item[]) => {
-        await mutateList(() => next);
-    };
-
     const openNativeMenu = async (e: React.MouseEvent, target: Item | null) => {
         console.log('openNativeMenu', { e, target });
         e.preventDefault();
@@ -146,29 +140,31 @@ export default function TreeExplorer() {
     const handleRootContext = (e: React.MouseEvent) => openNativeMenu(e, null);
 
     const createIn = async (parent: Item | null, kind: 'file' | 'folder') => {
-        const n = kind === 'file' ? createFile() : createFolder();
-        const next = addChild(userList, parent ? parent.id : null, n);
-        await apply(next);
-        if (kind === 'file') setCurrent(n);
+        const n = await createItem(
+            kind === 'file'
+                ? { name: '新建规则', on: true, type: 'file' }
+                : { name: '新建文件夹', on: true, type: 'folder' },
+            parent ? parent.id : null,
+        );
+        if (n && kind === 'file') setCurrent(n);
         // TreeView 自身展开控制: 目前依赖默认行为 (Radix Accordion state 自动管理)
     };
 
     const rename = async (id: string, name: string) => {
         if (!name) return;
-        await apply(updateItemName(userList, id, name));
+        await updateItem(id, { name });
     };
 
     const remove = async (node: Item) => {
         if (node.system) return;
         if (await confirm({ title: '删除', description: '确定删除该节点及其所有子节点？' })) {
-            await apply(removeItem(userList, node.id));
-            if (current?.id === node.id) setCurrent(null);
+            await deleteItem(node.id);
             await writeHostsToSystem();
         }
     };
 
     const setOn = async (id: string, on: boolean) => {
-        await apply(toggleItemOn(userList, id, on));
+        await updateItem(id, { on });
         await writeHostsToSystem();
     };
 
@@ -178,34 +174,14 @@ export default function TreeExplorer() {
 
     const doPaste = async (targetFolder: Item | null) => {
         if (!clipboard) return;
+        const parent = targetFolder ? targetFolder.id : null;
         if (clipboard.mode === 'copy') {
-            // copy structure and replicate contents for file nodes
-            const { items: nextItems, idMap } = pasteNodeWithMap(
-                userList,
-                targetFolder ? targetFolder.id : null,
-                clipboard.node,
-            );
-            await apply(nextItems);
-            // gather all source file ids (including nested) and copy content
-            const gather = (n: Item, acc: Item[]) => {
-                if (n.type === 'file') acc.push(n);
-                n.children?.forEach(c => gather(c, acc));
-                return acc;
-            };
-            const files = gather(clipboard.node, [] as Item[]);
-            for (const f of files) {
-                try {
-                    const content = await commands.getHostsContent(f.id);
-                    const newId = idMap[f.id];
-                    if (newId) await commands.setHostsContent(newId, content);
-                } catch (e) {
-                    console.warn('copy content failed', f.id, e);
-                }
-            }
+            // the backend copies the subtree together with its contents
+            const copy = await duplicateItem(clipboard.node.id);
+            if (copy) await moveItem(copy.id, parent);
         } else {
-            // cut -> move
-            const next = moveNode(userList, clipboard.node.id, targetFolder ? targetFolder.id : null);
-            await apply(next);
+            // cut -> move, ids stay stable
+            await moveItem(clipboard.node.id, parent);
             setClipboard(null);
         }
     };
@@ -281,7 +257,7 @@ export default function TreeExplorer() {
                     if (isRenaming) return;
                     if (it.type === 'file') setCurrent(it);
                 },
-                draggable: false, // TODO: enable drag & drop with moveNode
+                draggable: false, // TODO: enable drag & drop with moveItem
                 droppable: it.type === 'folder',
             };
             return node;
@@ -1,3 +1,7 @@
 export enum EVENTS {
-  SYSTEM_HOSTS_UPDATED = "SYSTEM_HOSTS_UPDATED"
+  SYSTEM_HOSTS_UPDATED = "SYSTEM_HOSTS_UPDATED",
+  // emitted by the backend when it changed list.json (e.g. rule schedules)
+  LIST_UPDATED = "LIST_UPDATED",
+  // a deep link is waiting; fetch it with takeDeepLink
+  DEEP_LINK = "DEEP_LINK"
 }
@@ -1,5 +1,6 @@
 import { useEffect, useRef, useState } from 'react';
-import commands from '@/commands';
+import { message } from '@suite/ui';
+import commands, { FlagError } from '@/commands';
 import writeHostsToSystem from '@/utils/writeHostsToSystem';
 import useEvent from './useEvent';
 import { EVENTS } from '@/events';
@@ -45,7 +46,12 @@ export default function useContent(id?: string) {
     content,
     contentId,
     updateContent: async (id: string, content: string) => {
-      await commands.setHostsContent(id, content);
+      try {
+        await commands.setHostsContent(id, content);
+      } catch (e) {
+        message.error(String((e as FlagError)?.message ?? e));
+        return;
+      }
       contentMapRef.current.set(id, content);
       await writeHostsToSystem();
     },
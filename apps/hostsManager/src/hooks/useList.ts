@@ -1,23 +1,100 @@
-import { useEffect, useState } from 'react';
-import commands from '@/commands';
+import { useEffect, useRef, useState } from 'react';
+import { confirm, message } from '@suite/ui';
+import commands, { ItemError, StoreError } from '@/commands';
 import { Item } from '@/typing';
 import { SYSTEM_HOST_ITEM } from '@/constants';
+import { EVENTS } from '@/events';
+import { findItem } from '@/utils/treeOps';
+import useEvent from './useEvent';
 
 export default function useList() {
   const [list, setList] = useState<Item[]>([]);
   const [current, setCurrent] = useState<Item | null>(SYSTEM_HOST_ITEM);
+  const revision = useRef<string | null>(null);
+
+  const reload = async () => {
+    const snap = await commands.getListWithRevision();
+    revision.current = snap.revision;
+    setList(snap.list as Item[]);
+    return snap.list as Item[];
+  };
+
+  // A shared rule is only added once the user has seen what it contains.
+  const importShared = async (data: string) => {
+    try {
+      const preview = await commands.previewSharedRule(data);
+      const notes = [`${preview.entries} 条记录`];
+      if (preview.warnings.length) notes.push(`${preview.warnings.length} 条警告`);
+      if (preview.existing.length) notes.push('已有同名规则，将另存为新规则');
+      const ok = await confirm({
+        title: `导入规则「${preview.title}」？`,
+        description: notes.join('，'),
+      });
+      if (!ok) return;
+      const item = (await commands.importSharedRule(data)) as Item;
+      await reload();
+      setCurrent(item);
+    } catch (e) {
+      message.error(String((e as ItemError)?.message ?? e));
+    }
+  };
+
+  // Deep links wait in the backend until taken, so one that started the app
+  // is picked up here on mount.
+  const followDeepLink = async (items: Item[]) => {
+    const link = await commands.takeDeepLink();
+    if (link?.action === 'import') {
+      await importShared(link.data);
+      return;
+    }
+    if (link?.action !== 'openRuleById') return;
+    const found = findItem(items, link.id);
+    if (found && found.type !== 'folder') {
+      setCurrent(found);
+    } else {
+      message.error(`未找到规则：${link.id}`);
+    }
+  };
 
   useEffect(() => {
-    const init = async () => {
-      const list: Item[] = await commands.getList();
-      setList(list);
-    };
-    init();
+    reload().then(followDeepLink);
   }, []);
 
+  useEvent(EVENTS.LIST_UPDATED, () => {
+    reload();
+  });
+
+  useEvent(EVENTS.DEEP_LINK, async () => {
+    followDeepLink(await reload());
+  });
+
   const updateList = async (next: Item[]) => {
-    const res = await commands.setList(next);
-    if (res) setList(next);
+    try {
+      revision.current = await commands.setList(next, revision.current);
+      setList(next);
+      return true;
+    } catch (e) {
+      const err = e as StoreError;
+      // someone else saved first; show their version instead of overwriting it
+      if (err?.code === 'conflict') {
+        message.error('列表已在其他地方被修改，已重新加载');
+        await reload();
+      } else {
+        message.error(String(err?.message ?? e));
+      }
+      return false;
+    }
+  };
+
+  // Runs a backend tree edit and reloads; resolves to undefined on failure.
+  const edit = async <T = void>(op: () => Promise<T>): Promise<T | undefined> => {
+    try {
+      return await op();
+    } catch (e) {
+      message.error(String((e as ItemError)?.message ?? e));
+    } finally {
+      await reload();
+    }
   };
 
   return {
@@ -25,36 +102,25 @@ export default function useList() {
     userList: list, // without system
     current,
     setCurrent,
-    updateList: commands.setList, // expects userList array
+    updateList, // expects userList array
     mutateList: async (fn: (items: Item[]) => Item[]) => {
       const next = fn(list);
       return updateList(next);
     },
-    createItem: async (item: Item) => {
-      const next = [...list, item];
-      return updateList(next);
-    },
-    updateItem: async (id: string, data: Partial<Item>) => {
-      // recursive update for tree
-      const walk = (arr: Item[]): Item[] => arr.map(it => {
-        if (it.id === id) return { ...it, ...data };
-        if (it.children) return { ...it, children: walk(it.children) };
-        return it;
-      });
-      const next = walk(list);
-      return updateList(next);
-    },
+    createItem: (item: Partial<Item>, parent: string | null = null) =>
+      edit<Item>(() => commands.createItem(parent, item)),
+    updateItem: (id: string, data: Partial<Item>) =>
+      edit<Item>(() => commands.updateItem(id, data)),
+    moveItem: (id: string, parent: string | null, index?: number) =>
+      edit(() => commands.moveItem(id, parent, index)),
+    duplicateItem: (id: string) => edit<Item>(() => commands.duplicateItem(id)),
+    importShared,
     deleteItem: async (id: string) => {
-      const walk = (arr: Item[]): Item[] => arr.filter(it => {
-        if (it.id === id) return false;
-        if (it.children) it.children = walk(it.children);
-        return true;
-      });
-      const next = walk(list);
-      if (current?.id === id) {
+      const removed = await edit<string[]>(() => commands.deleteItem(id));
+      if (current && removed?.includes(current.id)) {
         setCurrent(null); // let UI pick another later
       }
-      return updateList(next);
+      return removed;
     },
   };
 }
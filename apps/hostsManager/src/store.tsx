@@ -23,6 +23,8 @@ export function StoreProvider({ children }: { children?: ReactNode }) {
         mutateList,
         createItem,
         updateItem,
+        moveItem,
+        duplicateItem,
         deleteItem,
     } = useList();
 
@@ -39,6 +41,8 @@ export function StoreProvider({ children }: { children?: ReactNode }) {
                 updateList,
                 createItem,
                 updateItem,
+                moveItem,
+                duplicateItem,
                 deleteItem,
                 content,
                 contentId,
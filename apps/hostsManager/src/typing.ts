@@ -1,13 +1,32 @@
 
+export type Schedule = {
+  windows?: { start: string; end: string }[]; // "HH:MM" local time
+  weekdays?: number[]; // 1 = Monday .. 7 = Sunday
+  expires_at_ms?: number; // one-off: switched off by the backend after this
+  scheduled_on?: boolean; // kept by the backend: the state last switched to
+};
+
 export type Item = {
   id: string;
   name: string;
   on: boolean;
+  // enforced by the backend: `system` mirrors the system hosts file and cannot
+  // be deleted, `locked` makes the content read-only, `pinned` keeps it on
   system?: boolean;
+  locked?: boolean;
+  pinned?: boolean;
   // Tree support
-  type?: 'file' | 'folder'; // default file if undefined
+  type?: 'file' | 'folder' | 'remote'; // default file if undefined
   children?: Item[]; // only for folder
+  add_time_ms?: number; // set by the backend when the item is created
+  schedule?: Schedule;
+  priority?: number; // wins hostname conflicts against lower priorities, default 0
+  // remote / imported lists
+  url?: string;
+  format?: 'auto' | 'hosts' | 'adblock' | 'domains';
+  allowlist?: string[];
+  last_refresh_ms?: number;
 };
 
-// Note: legacy group fields removed; existing persisted JSON with groupId/color will be ignored gracefully.
+// Note: legacy group fields removed; the backend schema migration (v0 -> v1) strips groupId/color from persisted JSON.
 
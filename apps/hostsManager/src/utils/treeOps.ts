@@ -1,8 +1,7 @@
 import { Item } from '../typing';
-import { v4 as uuidV4 } from 'uuid';
 
-// Helper: deep clone simple
-const clone = <T>(v: T): T => JSON.parse(JSON.stringify(v));
+// Tree edits (create/move/duplicate/delete) live in the backend; see the
+// *Item commands in commands.ts.
 
 export type TreePath = string[]; // sequence of ids
 
@@ -16,133 +15,3 @@ export function findItem(items: Item[], id: string): Item | null {
   }
   return null;
 }
-
-export function addChild(items: Item[], parentId: string | null, node: Item): Item[] {
-  const next = clone(items);
-  if (!parentId) {
-    next.push(node);
-    return next;
-  }
-  const walk = (arr: Item[]): boolean => {
-    for (const it of arr) {
-      if (it.id === parentId) {
-        if (it.type !== 'folder') {
-          it.type = 'folder';
-          it.children = it.children || [];
-        }
-        it.children = it.children || [];
-        it.children.push(node);
-        return true;
-      }
-      if (it.children && walk(it.children)) return true;
-    }
-    return false;
-  };
-  walk(next);
-  return next;
-}
-
-export function removeItem(items: Item[], id: string): Item[] {
-  const next = clone(items);
-  const walk = (arr: Item[]): Item[] => {
-    return arr.filter(it => {
-      if (it.id === id) return false;
-      if (it.children) it.children = walk(it.children);
-      return true;
-    });
-  };
-  return walk(next);
-}
-
-export function updateItemName(items: Item[], id: string, name: string): Item[] {
-  const next = clone(items);
-  const walk = (arr: Item[]) => {
-    for (const it of arr) {
-      if (it.id === id) {
-        it.name = name;
-      }
-      if (it.children) walk(it.children);
-    }
-  };
-  walk(next);
-  return next;
-}
-
-export function toggleItemOn(items: Item[], id: string, on: boolean): Item[] {
-  const next = clone(items);
-  const walk = (arr: Item[]) => {
-    for (const it of arr) {
-      if (it.id === id) {
-        it.on = on;
-      }
-      if (it.children) walk(it.children);
-    }
-  };
-  walk(next);
-  return next;
-}
-
-export function createFile(name = '新建规则'): Item {
-  return { id: uuidV4(), name, on: true, type: 'file' };
-}
-export function createFolder(name = '新建文件夹'): Item {
-  return { id: uuidV4(), name, on: true, type: 'folder', children: [] };
-}
-
-export function duplicateSubtree(node: Item): Item {
-  const copy = clone(node);
-  const remap = (n: Item) => {
-    n.id = uuidV4();
-    if (n.children) n.children.forEach(remap);
-  };
-  remap(copy);
-  return copy;
-}
-
-// duplicate with id mapping (oldId -> newId)
-export function duplicateSubtreeWithMap(node: Item): { copy: Item; idMap: Record<string,string> } {
-  const copy = clone(node);
-  const idMap: Record<string,string> = {};
-  const remap = (n: Item) => {
-    const old = n.id;
-    n.id = uuidV4();
-    idMap[old] = n.id;
-    if (n.children) n.children.forEach(remap);
-  };
-  remap(copy);
-  return { copy, idMap };
-}
-
-// Paste (copy) or move (cut) under a folder (or root parentId null)
-export function pasteNode(items: Item[], targetFolderId: string | null, node: Item): Item[] {
-  const next = clone(items);
-  const newNode = duplicateSubtree(node);
-  return addChild(next, targetFolderId, newNode);
-}
-
-// paste with id map (for copying associated contents)
-export function pasteNodeWithMap(items: Item[], targetFolderId: string | null, node: Item): { items: Item[]; idMap: Record<string,string>; newRootId: string } {
-  const next = clone(items);
-  const { copy, idMap } = duplicateSubtreeWithMap(node);
-  const added = addChild(next, targetFolderId, copy);
-  return { items: added, idMap, newRootId: copy.id };
-}
-
-// Move (cut) by removing original then addChild (keeping id?) -> we keep ids stable when moving
-export function moveNode(items: Item[], nodeId: string, targetFolderId: string | null): Item[] {
-  const next = clone(items);
-  let moving: Item | null = null;
-  const extract = (arr: Item[]): Item[] => {
-    return arr.filter(it => {
-      if (it.id === nodeId) {
-        moving = it;
-        return false;
-      }
-      if (it.children) it.children = extract(it.children);
-      return true;
-    });
-  };
-  const stripped = extract(next);
-  if (!moving) return items; // not found
-  return addChild(stripped, targetFolderId, moving);
-}
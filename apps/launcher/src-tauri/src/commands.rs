@@ -108,6 +108,10 @@ pub struct AppDescriptor {
     pub description: Option<String>,
     pub version: Option<String>,
     pub scheme: Option<String>,
+    /// Opt-in: the argument in `args` that names the action to run. Apps
+    /// without it only ever receive an action's own arguments.
+    #[serde(rename = "actionArg", default, skip_serializing_if = "Option::is_none")]
+    pub action_arg: Option<String>,
     #[serde(default)]
     pub actions: Vec<AppAction>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -8,6 +8,7 @@ type AppDescriptor = {
     id: string;
     name?: string;
     description?: string;
+    actionArg?: string;
     actions?: AppAction[];
     icon?: string;
 };
@@ -31,19 +32,19 @@ export default function App() {
     };
 
     const runAction = async (app: AppDescriptor, action: AppAction) => {
-        let argsQuery = '';
-        const argSpecs = action.args || [];
-        if (argSpecs.length > 0) {
-            const parts: string[] = [];
-            for (const spec of argSpecs) {
-                const v =
-                    window.prompt(`请输入参数 ${spec.name}${spec.required ? ' (必填)' : ''}`) ?? '';
-                if (v || spec.required) {
-                    parts.push(`${spec.name}=${encodeURIComponent(v)}`);
-                }
+        // apps that declare `actionArg` pick the action from that argument;
+        // `open` is their default
+        const parts: string[] =
+            app.actionArg && action.name !== 'open'
+                ? [`${app.actionArg}=${encodeURIComponent(action.name)}`]
+                : [];
+        for (const spec of action.args || []) {
+            const v = window.prompt(`请输入参数 ${spec.name}${spec.required ? ' (必填)' : ''}`) ?? '';
+            if (v || spec.required) {
+                parts.push(`${spec.name}=${encodeURIComponent(v)}`);
             }
-            argsQuery = parts.join('&');
         }
+        const argsQuery = parts.join('&');
         await invoke('open_with_args', { appName: app.id, args: argsQuery || undefined });
     };
 